@@ -2,10 +2,16 @@ pub mod index;
 pub mod p_var;
 pub mod sessions;
 
-pub use index::Index;
+use chashmap::CHashMap;
+pub use index::{Index, IndexNotifier};
+use once_cell::sync::Lazy;
 pub use p_var::PVar;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
+static INSTANCE_COUNTS: Lazy<CHashMap<&'static str, usize>> = Lazy::new(CHashMap::new);
+
 // ------ ActorId ------
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Hash)]
@@ -28,3 +34,25 @@ pub trait ActorInstance {
 
     fn remove(&self);
 }
+
+// ------ instance counts ------
+
+/// Call when an actor instance has been created to make it visible in the admin API.
+pub fn instance_created<A: ActorInstance>() {
+    INSTANCE_COUNTS.upsert(A::KEY, || 1, |count| *count += 1);
+}
+
+/// Call when an actor instance has been removed.
+pub fn instance_removed<A: ActorInstance>() {
+    INSTANCE_COUNTS.alter(A::KEY, |count| count.map(|count| count.saturating_sub(1)));
+}
+
+/// Instance counts by `ActorInstance::KEY`.
+pub fn instance_counts() -> BTreeMap<&'static str, usize> {
+    let counts = RefCell::new(BTreeMap::new());
+    INSTANCE_COUNTS.retain(|key, count| {
+        counts.borrow_mut().insert(*key, *count);
+        true
+    });
+    counts.into_inner()
+}
@@ -1,6 +1,13 @@
 use crate::actor::{ActorId, PVar};
 use async_trait::async_trait;
 use std::borrow::Borrow;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::{self, Instant};
+
+const DEFAULT_WAIT_FOR_TIMEOUT: Duration = Duration::from_secs(10);
+// Used only by indices without a notifier.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 #[async_trait(?Send)]
 pub trait Index {
@@ -19,19 +26,64 @@ pub trait Index {
         todo!()
     }
 
+    /// `wait_for` falls back to polling when the index doesn't have a notifier.
+    fn notifier(&self) -> Option<&IndexNotifier> {
+        None
+    }
+
     async fn wait_for(
         &self,
         key: impl Borrow<<Self::PVar as PVar>::Value> + 'static,
     ) -> Option<Self::Actor> {
-        // @TODO backoff + jitter + queue or something else?
+        self.wait_for_with_timeout(key, DEFAULT_WAIT_FOR_TIMEOUT)
+            .await
+    }
+
+    async fn wait_for_with_timeout(
+        &self,
+        key: impl Borrow<<Self::PVar as PVar>::Value> + 'static,
+        timeout: Duration,
+    ) -> Option<Self::Actor> {
         let key = key.borrow();
-        for i in 0..10 {
-            let session = self.get(key);
-            if session.is_some() {
-                return session;
+        let deadline = Instant::now() + timeout;
+        loop {
+            // The `Notified` future has to be created before `get`
+            // to not miss an `insert` called between them.
+            let notified = self.notifier().map(IndexNotifier::notified);
+            let actor = self.get(key);
+            if actor.is_some() {
+                return actor;
+            }
+            let timed_out = match notified {
+                Some(notified) => time::timeout_at(deadline, notified).await.is_err(),
+                None => {
+                    time::sleep_until(deadline.min(Instant::now() + POLL_INTERVAL)).await;
+                    Instant::now() >= deadline
+                }
+            };
+            if timed_out {
+                return self.get(key);
             }
-            tokio::time::sleep(tokio::time::Duration::from_millis(i * 200)).await;
         }
-        None
+    }
+}
+
+// ------ IndexNotifier ------
+
+/// Wakes up `Index::wait_for` calls. Call `notify` in `Index::insert`.
+#[derive(Debug, Default)]
+pub struct IndexNotifier(Notify);
+
+impl IndexNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn notify(&self) {
+        self.0.notify_waiters();
+    }
+
+    fn notified(&self) -> tokio::sync::futures::Notified<'_> {
+        self.0.notified()
     }
 }
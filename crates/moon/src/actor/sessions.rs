@@ -1,12 +1,20 @@
-use crate::actor::{ActorId, ActorInstance, Index, PVar};
+use crate::actor::{self, ActorId, ActorInstance, Index, IndexNotifier, PVar};
 use crate::sse::ShareableSSEMethods;
 use crate::MessageSSE;
 use chashmap::CHashMap;
-use futures::future::join_all;
-use moonlight::{serde_json, CorId, DownMsgTransporterForSer, Serialize, SessionId};
+use futures::{future::join_all, pin_mut, Stream, StreamExt};
+use moonlight::chrono::{DateTime, Utc};
+use moonlight::{serde_json, CorId, DownMsgTransporterForSer, Serialize, ServerError, SessionId};
 use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use std::borrow::Borrow;
 use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+// zoon retries failed requests with the same `CorId`.
+const MAX_RECENT_COR_IDS: usize = 256;
 
 // @TODO rewrite to a proper virtual actor
 
@@ -18,9 +26,34 @@ pub async fn broadcast_down_msg<DMsg: Serialize>(down_msg: &DMsg, cor_id: CorId)
     join_all(send_down_msg_futs).await;
 }
 
+// ------ SessionInfo ------
+
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub session_id: SessionId,
+    pub connected_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+}
+
+pub fn infos() -> Vec<SessionInfo> {
+    let infos = RefCell::new(Vec::new());
+    SESSION_ACTOR_INSTANCES.retain(|_, instance| {
+        if let Some(session_id) = instance.session_id.read() {
+            infos.borrow_mut().push(SessionInfo {
+                session_id,
+                connected_at: instance.connected_at,
+                last_activity: *instance.last_activity.lock(),
+            });
+        }
+        true
+    });
+    infos.into_inner()
+}
+
 // ------ Indices ------
 
 static BY_SESSION_ID: Lazy<CHashMap<SessionId, SessionActor>> = Lazy::new(CHashMap::new);
+static BY_SESSION_ID_NOTIFIER: Lazy<IndexNotifier> = Lazy::new(IndexNotifier::new);
 
 pub const fn by_session_id() -> BySessionId {
     BySessionId
@@ -34,6 +67,7 @@ impl Index for BySessionId {
 
     fn insert(&self, key: <Self::PVar as PVar>::Value, actor_id: ActorId) {
         BY_SESSION_ID.insert(key, SessionActor { actor_id });
+        BY_SESSION_ID_NOTIFIER.notify();
     }
 
     fn get(&self, key: impl Borrow<<Self::PVar as PVar>::Value>) -> Option<Self::Actor> {
@@ -42,6 +76,10 @@ impl Index for BySessionId {
             .map(|session_actor| *session_actor)
     }
 
+    fn notifier(&self) -> Option<&IndexNotifier> {
+        Some(&BY_SESSION_ID_NOTIFIER)
+    }
+
     fn for_each(&self, f: impl FnMut(SessionId, SessionActor)) {
         let f = RefCell::new(f);
         BY_SESSION_ID.retain(|session_id, session_actor| {
@@ -103,6 +141,86 @@ impl SessionActor {
         }
     }
 
+    /// Returns `false` if the session has been already removed.
+    pub(crate) fn resume(&self) -> bool {
+        match SESSION_ACTOR_INSTANCES.get(&self.actor_id) {
+            Some(instance) => {
+                *instance.last_activity.lock() = Utc::now();
+                if let Some(session_id) = instance.session_id.read() {
+                    println!("Session `{}` resumed.", session_id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `false` if the `CorId` has been already registered,
+    /// i.e. the request is a retry of an already handled `UpMsg`.
+    pub(crate) fn register_cor_id(&self, cor_id: CorId) -> bool {
+        match SESSION_ACTOR_INSTANCES.get(&self.actor_id) {
+            Some(instance) => {
+                let mut recent_cor_ids = instance.recent_cor_ids.lock();
+                if recent_cor_ids.contains(&cor_id) {
+                    return false;
+                }
+                if recent_cor_ids.len() == MAX_RECENT_COR_IDS {
+                    recent_cor_ids.pop_front();
+                }
+                recent_cor_ids.push_back(cor_id);
+                true
+            }
+            None => true,
+        }
+    }
+
+    /// Called when zoon has stopped waiting for the `DownMsg`,
+    /// e.g. `exchange_msgs` timed out.
+    pub(crate) fn cancel(&self, cor_id: CorId) {
+        if let Some(instance) = SESSION_ACTOR_INSTANCES.get(&self.actor_id) {
+            let mut cancelled_cor_ids = instance.cancelled_cor_ids.lock();
+            if !cancelled_cor_ids.contains(&cor_id) {
+                if cancelled_cor_ids.len() == MAX_RECENT_COR_IDS {
+                    cancelled_cor_ids.pop_front();
+                }
+                cancelled_cor_ids.push_back(cor_id);
+            }
+            instance.cancellation_notify.notify_waiters();
+        }
+    }
+
+    /// A removed session is considered cancelled as well.
+    pub fn is_cancelled(&self, cor_id: CorId) -> bool {
+        match SESSION_ACTOR_INSTANCES.get(&self.actor_id) {
+            Some(instance) => instance.cancelled_cor_ids.lock().contains(&cor_id),
+            None => true,
+        }
+    }
+
+    /// Resolves when the `UpMsg` with the given `CorId` has been cancelled.
+    pub async fn cancelled(&self, cor_id: CorId) {
+        // We don't want to hold the instance lock while waiting.
+        let cancellation_notify = match SESSION_ACTOR_INSTANCES.get(&self.actor_id) {
+            Some(instance) => Arc::clone(&instance.cancellation_notify),
+            None => return,
+        };
+        loop {
+            // Created before the check to not miss a notification.
+            let notified = cancellation_notify.notified();
+            if self.is_cancelled(cor_id) {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Updates the last activity time shown in the admin API.
+    pub fn touch(&self) {
+        if let Some(instance) = SESSION_ACTOR_INSTANCES.get(&self.actor_id) {
+            *instance.last_activity.lock() = Utc::now();
+        }
+    }
+
     pub(crate) fn remove(&self) {
         if let Some(instance) = SESSION_ACTOR_INSTANCES.remove(&self.actor_id) {
             let session_id = instance.session_id.read();
@@ -122,6 +240,55 @@ impl SessionActor {
             instance.send_down_msg(down_msg, cor_id).await;
         }
     }
+
+    /// Sent instead of a `DownMsg`, zoon resolves the related `exchange_msgs` call
+    /// with `ExchangeMsgsError::Backend`.
+    pub async fn send_server_error(&self, error: ServerError, cor_id: CorId) {
+        if let Some(instance) = SESSION_ACTOR_INSTANCES.get(&self.actor_id) {
+            instance.send_server_error(error, cor_id).await;
+        }
+    }
+
+    /// Sends `down_msgs` one by one and then the end-of-stream marker.
+    /// zoon receives them through `Connection::exchange_msgs_stream`.
+    pub async fn send_down_msg_stream<DMsg: Serialize>(
+        &self,
+        down_msgs: impl Stream<Item = DMsg>,
+        cor_id: CorId,
+    ) {
+        // We don't want to hold the instance lock while waiting for stream items.
+        let (message_sse, session_id) = match SESSION_ACTOR_INSTANCES.get(&self.actor_id) {
+            Some(instance) => match instance.session_id.read() {
+                Some(session_id) => (instance.message_sse.clone(), session_id),
+                None => return,
+            },
+            None => return,
+        };
+        pin_mut!(down_msgs);
+        while let Some(down_msg) = down_msgs.next().await {
+            // Backpressure - we don't want to drop stream items when the client is slow.
+            match message_sse.connection(&session_id) {
+                Some(connection) => connection.wait_for_capacity().await,
+                None => return,
+            }
+            let down_msg_transporter = serialize_down_msg_transporter(&down_msg, cor_id);
+            let sent = message_sse.send_correlated(
+                &session_id,
+                "down_msg_stream",
+                &down_msg_transporter,
+                cor_id,
+            );
+            if !matches!(sent, Some(Ok(()))) {
+                return;
+            }
+        }
+        message_sse.send_correlated(
+            &session_id,
+            "down_msg_stream_end",
+            &cor_id.to_string(),
+            cor_id,
+        );
+    }
 }
 
 // -- SessionActorInstance --
@@ -133,6 +300,11 @@ struct SessionActorInstance {
     actor_id: ActorId,
     message_sse: MessageSSE,
     session_id: PVarSessionId,
+    connected_at: DateTime<Utc>,
+    last_activity: Mutex<DateTime<Utc>>,
+    recent_cor_ids: Mutex<VecDeque<CorId>>,
+    cancelled_cor_ids: Mutex<VecDeque<CorId>>,
+    cancellation_notify: Arc<Notify>,
 }
 
 impl ActorInstance for SessionActorInstance {
@@ -147,8 +319,18 @@ impl ActorInstance for SessionActorInstance {
     }
 
     fn remove(&self) {
+        if let Some(session_id) = self.session_id.read() {
+            // The entry may already belong to a new actor with the same session id.
+            let actor_id = self.actor_id;
+            BY_SESSION_ID.alter(session_id, |session_actor| {
+                session_actor.filter(|session_actor| session_actor.actor_id != actor_id)
+            });
+        }
         self.session_id.remove();
         SESSION_ACTOR_INSTANCES.remove(&self.actor_id);
+        actor::instance_removed::<Self>();
+        // Handlers waiting in `SessionActor::cancelled` see the removed session as cancelled.
+        self.cancellation_notify.notify_waiters();
     }
 }
 
@@ -158,12 +340,19 @@ impl SessionActorInstance {
 
         by_session_id().insert(session_id, actor_id);
 
+        let now = Utc::now();
         let actor_instance = Self {
             actor_id,
             message_sse,
             session_id: PVarSessionId(actor_id).create(session_id),
+            connected_at: now,
+            last_activity: Mutex::new(now),
+            recent_cor_ids: Mutex::new(VecDeque::new()),
+            cancelled_cor_ids: Mutex::new(VecDeque::new()),
+            cancellation_notify: Arc::new(Notify::new()),
         };
         SESSION_ACTOR_INSTANCES.insert(actor_id, actor_instance);
+        actor::instance_created::<Self>();
 
         println!(
             "New session: `{}`. (Session count: {})",
@@ -175,17 +364,36 @@ impl SessionActorInstance {
 
     pub async fn send_down_msg<DMsg: Serialize>(&self, down_msg: &DMsg, cor_id: CorId) {
         let session_id = self.session_id.read().unwrap();
+        let down_msg_transporter = serialize_down_msg_transporter(down_msg, cor_id);
+        self.message_sse
+            .send_correlated(&session_id, "down_msg", &down_msg_transporter, cor_id);
+    }
+
+    pub async fn send_server_error(&self, error: ServerError, cor_id: CorId) {
+        let session_id = self.session_id.read().unwrap();
+        let down_msg_transporter = serialize_server_error(error, cor_id);
+        self.message_sse
+            .send_correlated(&session_id, "down_msg", &down_msg_transporter, cor_id);
+    }
+}
 
-        let down_msg_transporter = DownMsgTransporterForSer { down_msg, cor_id };
+fn serialize_down_msg_transporter<DMsg: Serialize>(down_msg: &DMsg, cor_id: CorId) -> String {
+    serialize_transporter(&DownMsgTransporterForSer::Ok { down_msg, cor_id })
+}
 
-        #[cfg(feature = "serde-lite")]
-        let down_msg_transporter =
-            serde_json::to_string(&down_msg_transporter.serialize().unwrap()).unwrap();
+pub(crate) fn serialize_server_error(error: ServerError, cor_id: CorId) -> String {
+    serialize_transporter(&DownMsgTransporterForSer::<()>::Err { error, cor_id })
+}
 
-        #[cfg(feature = "serde")]
-        let down_msg_transporter = serde_json::to_string(&down_msg_transporter).unwrap();
+fn serialize_transporter<DMsg: Serialize>(
+    down_msg_transporter: &DownMsgTransporterForSer<DMsg>,
+) -> String {
+    #[cfg(feature = "serde-lite")]
+    let down_msg_transporter =
+        serde_json::to_string(&down_msg_transporter.serialize().unwrap()).unwrap();
 
-        self.message_sse
-            .send(&session_id, "down_msg", &down_msg_transporter);
-    }
+    #[cfg(feature = "serde")]
+    let down_msg_transporter = serde_json::to_string(down_msg_transporter).unwrap();
+
+    down_msg_transporter
 }
@@ -0,0 +1,141 @@
+//! Token-protected admin API for debugging running apps.
+//! It's enabled only when `ADMIN_TOKEN` is set.
+//!
+//! - `GET /_api/admin` - a tiny static HTML page displaying the data below
+//! - `GET /_api/admin/status` - sessions, actor instance counts and SSE queue metrics
+//! - `DELETE /_api/admin/sessions/{session_id}` - closes the session
+//!
+//! Data endpoints require the `Authorization: Bearer {ADMIN_TOKEN}` header.
+
+use crate::actor::{self, Index};
+use crate::config::CONFIG;
+use crate::sse::{SSEMetrics, ShareableSSEMethods};
+use crate::{sessions, MessageSSE};
+use actix_web::http::header::{self, CacheControl, CacheDirective, ContentType};
+use actix_web::{error, web, Error, HttpRequest, HttpResponse, Scope};
+use moonlight::chrono::{DateTime, Utc};
+use moonlight::SessionId;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub(crate) fn scope() -> Scope {
+    web::scope("admin")
+        .route("", web::get().to(page_responder))
+        .route("/status", web::get().to(status_responder))
+        .route(
+            "/sessions/{session_id}",
+            web::delete().to(close_session_responder),
+        )
+}
+
+fn authorize(req: &HttpRequest) -> Result<(), Error> {
+    let token = CONFIG
+        .admin
+        .enabled_token()
+        .ok_or_else(|| error::ErrorNotFound("API Not Found"))?;
+
+    let provided_token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok())
+        .and_then(|authorization| authorization.strip_prefix("Bearer "));
+
+    match provided_token {
+        Some(provided_token) if constant_time_eq(provided_token, token) => Ok(()),
+        _ => Err(error::ErrorUnauthorized("invalid admin token")),
+    }
+}
+
+// We don't want to leak the token length or its prefix through response times.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// ------ page_responder ------
+
+// The page is plain HTML + JS instead of a Zoon app on purpose - Moon would have to ship
+// a prebuilt Wasm bundle and the page would break whenever Zoon and the app versions differ.
+async fn page_responder() -> Result<HttpResponse, Error> {
+    if CONFIG.admin.enabled_token().is_none() {
+        Err(error::ErrorNotFound("API Not Found"))?
+    }
+    Ok(HttpResponse::Ok()
+        .insert_header(ContentType::html())
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(include_str!("../html/admin.html")))
+}
+
+// ------ status_responder ------
+
+#[derive(Serialize)]
+struct Status {
+    sessions: Vec<Session>,
+    actors: BTreeMap<&'static str, usize>,
+    sse: SSEMetrics,
+}
+
+#[derive(Serialize)]
+struct Session {
+    session_id: String,
+    connected_at: DateTime<Utc>,
+    last_activity: DateTime<Utc>,
+    queue_depth: Option<usize>,
+}
+
+async fn status_responder(
+    req: HttpRequest,
+    message_sse: web::Data<MessageSSE>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req)?;
+
+    let mut sessions = sessions::infos()
+        .into_iter()
+        .map(|info| Session {
+            session_id: info.session_id.to_string(),
+            connected_at: info.connected_at,
+            last_activity: info.last_activity,
+            queue_depth: message_sse
+                .connection(&info.session_id)
+                .map(|connection| connection.queue_depth()),
+        })
+        .collect::<Vec<_>>();
+    sessions.sort_by_key(|session| session.connected_at);
+
+    let status = Status {
+        sessions,
+        actors: actor::instance_counts(),
+        sse: message_sse.metrics(),
+    };
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(status))
+}
+
+// ------ close_session_responder ------
+
+async fn close_session_responder(
+    req: HttpRequest,
+    session_id: web::Path<String>,
+    message_sse: web::Data<MessageSSE>,
+) -> Result<HttpResponse, Error> {
+    authorize(&req)?;
+
+    let session_id = session_id
+        .parse::<SessionId>()
+        .map_err(error::ErrorBadRequest)?;
+    let session_actor = sessions::by_session_id()
+        .get(session_id)
+        .ok_or_else(|| error::ErrorNotFound("Session Not Found"))?;
+
+    // The connection removal removes the session actor as well,
+    // but the actor may exist without the connection.
+    message_sse.remove_connection(&session_id);
+    session_actor.remove();
+
+    log::warn!("Session '{session_id}' closed through the admin API");
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,8 +1,19 @@
 use crate::from_env_vars::FromEnvVars;
+use crate::redirect::TrailingSlash;
+use crate::sse::OverflowPolicy;
 use log::LevelFilter;
 pub use once_cell::sync::Lazy;
-use serde::Deserialize;
-use std::{borrow::Cow, collections::BTreeSet};
+use serde::{Deserialize, Deserializer};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+};
+
+mod custom;
+pub use custom::{custom, custom_or_exit, CustomConfigError, EnvVarsError};
 
 pub static CONFIG: Lazy<Config> = Lazy::new(Config::from_env_vars);
 
@@ -11,22 +22,43 @@ pub static CONFIG: Lazy<Config> = Lazy::new(Config::from_env_vars);
 pub struct Config {
     // PORT
     pub port: u16,
+    // BIND_ADDRESS="127.0.0.1,[::1]"
+    #[serde(deserialize_with = "deserialize_bind_address")]
+    pub bind_address: Vec<IpAddr>,
+    // UNIX_SOCKET="/run/moon/app.sock"
+    // The server listens only on the socket when it's set (e.g. behind nginx).
+    pub unix_socket: Option<PathBuf>,
     // HTTPS
     pub https: bool,
     // COMPRESSED_PKG
     pub compressed_pkg: bool,
+    // COMPRESSED_PUBLIC
+    pub compressed_public: bool,
+    // FINGERPRINTED_PUBLIC
+    pub fingerprinted_public: bool,
     // CACHE_BUSTING
     pub cache_busting: bool,
     // BACKEND_LOG_LEVEL
     pub backend_log_level: LevelFilter,
     // FRONTEND_DIST
     pub frontend_dist: bool,
+    // UP_MSG_HANDLER_TIMEOUT (seconds, 0 disables the timeout)
+    pub up_msg_handler_timeout: u64,
 
     #[serde(default = "Redirect::from_env_vars")]
     pub redirect: Redirect,
 
     #[serde(default = "Cors::from_env_vars")]
     pub cors: Cors,
+
+    #[serde(default = "Tls::from_env_vars")]
+    pub tls: Tls,
+
+    #[serde(default = "Sse::from_env_vars")]
+    pub sse: Sse,
+
+    #[serde(default = "Admin::from_env_vars")]
+    pub admin: Admin,
 }
 
 impl FromEnvVars for Config {
@@ -37,17 +69,46 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             port: 8080,
+            bind_address: vec![Ipv4Addr::UNSPECIFIED.into()],
+            unix_socket: None,
             https: false,
             compressed_pkg: true,
+            compressed_public: false,
+            fingerprinted_public: false,
             cache_busting: true,
             backend_log_level: LevelFilter::Warn,
             frontend_dist: false,
+            up_msg_handler_timeout: 60,
             redirect: Redirect::default(),
             cors: Cors::default(),
+            tls: Tls::default(),
+            sse: Sse::default(),
+            admin: Admin::default(),
         }
     }
 }
 
+// IPv6 addresses may be written with brackets, e.g. `[::]`.
+fn deserialize_bind_address<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<IpAddr>, D::Error> {
+    let addresses = String::deserialize(deserializer)?;
+    addresses
+        .split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| {
+            address
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .map_err(|error| {
+                    serde::de::Error::custom(format!("invalid bind address '{address}': {error}"))
+                })
+        })
+        .collect()
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct Redirect {
@@ -55,6 +116,33 @@ pub struct Redirect {
     pub port: u16,
     // REDIRECT_ENABLED
     pub enabled: bool,
+    // REDIRECT_CANONICAL_HOST="example.com"
+    pub canonical_host: Option<String>,
+    // REDIRECT_TRAILING_SLASH="keep" / "add" / "remove"
+    pub trailing_slash: TrailingSlash,
+    // REDIRECT_PERMANENT='"/old-page" = "/new-page"
+    // "/blog" = "https://blog.example.com"' (TOML table, one redirect per line)
+    #[serde(deserialize_with = "deserialize_redirect_table")]
+    pub permanent: BTreeMap<String, String>,
+}
+
+impl Redirect {
+    pub fn has_rules(&self) -> bool {
+        self.canonical_host.is_some()
+            || self.trailing_slash != TrailingSlash::Keep
+            || !self.permanent.is_empty()
+    }
+}
+
+fn deserialize_redirect_table<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, String>, D::Error> {
+    let table = String::deserialize(deserializer)?;
+    toml::from_str(&table).map_err(|error| {
+        serde::de::Error::custom(format!(
+            "invalid redirect table, expected lines like '\"/from\" = \"/to\"': {error}"
+        ))
+    })
 }
 
 impl FromEnvVars for Redirect {
@@ -67,6 +155,9 @@ impl Default for Redirect {
         Self {
             port: 8081,
             enabled: false,
+            canonical_host: None,
+            trailing_slash: TrailingSlash::default(),
+            permanent: BTreeMap::new(),
         }
     }
 }
@@ -76,17 +167,190 @@ impl Default for Redirect {
 pub struct Cors {
     // CORS_ORIGINS="http://localhost:8080,http://127.0.0.1:8080,*"
     pub origins: BTreeSet<Cow<'static, str>>,
+    // CORS_CREDENTIALS
+    // Requires explicit origins, it can't be combined with the "*" origin.
+    pub credentials: bool,
+    // CORS_ALLOWED_HEADERS="Content-Type,X-Session-ID,X-Correlation-ID,X-Auth-Token" or "*"
+    pub allowed_headers: BTreeSet<Cow<'static, str>>,
+    // CORS_EXPOSED_HEADERS="X-Request-ID"
+    pub exposed_headers: BTreeSet<Cow<'static, str>>,
+    // CORS_MAX_AGE=3600 (seconds)
+    pub max_age: Option<usize>,
+}
+
+impl Cors {
+    // Any website could send requests with the user's cookies otherwise,
+    // because the allowed origin is the request's origin.
+    fn check_credentials(&self) -> Result<(), String> {
+        if self.credentials && self.origins.contains("*") {
+            return Err(String::from(
+                "credentials can't be allowed for the \"*\" origin, list the allowed origins explicitly",
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl FromEnvVars for Cors {
     const ENTITY_NAME: &'static str = "Cors";
     const ENV_PREFIX: &'static str = "CORS_";
+
+    fn try_from_env_vars() -> Result<Self, envy::Error> {
+        let cors = envy::prefixed(Self::ENV_PREFIX).from_env::<Self>()?;
+        cors.check_credentials().map_err(envy::Error::Custom)?;
+        Ok(cors)
+    }
 }
 
 impl Default for Cors {
     fn default() -> Self {
         Self {
             origins: BTreeSet::from_iter(["*".into()]),
+            credentials: false,
+            allowed_headers: BTreeSet::from_iter([
+                "Content-Type".into(),
+                "X-Session-ID".into(),
+                "X-Correlation-ID".into(),
+                "X-Auth-Token".into(),
+            ]),
+            exposed_headers: BTreeSet::new(),
+            max_age: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Tls {
+    // TLS_RELOAD
+    pub reload: bool,
+    // TLS_SNI_HOSTS="example.com,www.example.com"
+    // Certificates are loaded from `backend/private/{host}/public.pem` and `private.pem`.
+    pub sni_hosts: BTreeSet<Cow<'static, str>>,
+}
+
+impl FromEnvVars for Tls {
+    const ENTITY_NAME: &'static str = "Tls";
+    const ENV_PREFIX: &'static str = "TLS_";
+}
+
+impl Default for Tls {
+    fn default() -> Self {
+        Self {
+            reload: true,
+            sni_hosts: BTreeSet::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Sse {
+    // SSE_QUEUE_CAPACITY (messages per connection)
+    pub queue_capacity: usize,
+    // SSE_OVERFLOW_POLICY="drop_oldest" / "coalesce" / "disconnect"
+    pub overflow_policy: OverflowPolicy,
+}
+
+impl FromEnvVars for Sse {
+    const ENTITY_NAME: &'static str = "Sse";
+    const ENV_PREFIX: &'static str = "SSE_";
+}
+
+impl Default for Sse {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 1000,
+            overflow_policy: OverflowPolicy::default(),
         }
     }
 }
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Admin {
+    // ADMIN_TOKEN
+    // The admin API (`/_api/admin`) is disabled when the token isn't set.
+    pub token: Option<String>,
+}
+
+impl Admin {
+    pub fn enabled_token(&self) -> Option<&str> {
+        self.token.as_deref().filter(|token| !token.is_empty())
+    }
+}
+
+// The token mustn't be printed with the rest of the config.
+impl fmt::Debug for Admin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Admin")
+            .field("enabled", &self.enabled_token().is_some())
+            .finish()
+    }
+}
+
+impl FromEnvVars for Admin {
+    const ENTITY_NAME: &'static str = "Admin";
+    const ENV_PREFIX: &'static str = "ADMIN_";
+}
+
+// ====== ====== TESTS ====== ======
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::{value, IntoDeserializer};
+    use std::net::Ipv6Addr;
+
+    #[test]
+    fn test_bind_address() {
+        let parse = |addresses: &str| {
+            deserialize_bind_address(IntoDeserializer::<value::Error>::into_deserializer(
+                addresses,
+            ))
+        };
+
+        assert_eq!(
+            parse("127.0.0.1, [::]").unwrap(),
+            vec![
+                IpAddr::from(Ipv4Addr::LOCALHOST),
+                Ipv6Addr::UNSPECIFIED.into()
+            ]
+        );
+        assert!(parse("localhost").is_err());
+    }
+
+    #[test]
+    fn test_cors_credentials() {
+        let cors = |origins: &[&'static str], credentials| Cors {
+            origins: origins.iter().map(|origin| Cow::from(*origin)).collect(),
+            credentials,
+            ..Cors::default()
+        };
+
+        assert!(cors(&["*"], false).check_credentials().is_ok());
+        assert!(cors(&["https://example.com"], true)
+            .check_credentials()
+            .is_ok());
+        assert!(cors(&["https://example.com", "*"], true)
+            .check_credentials()
+            .is_err());
+    }
+
+    #[test]
+    fn test_redirect_table() {
+        let parse = |table: &str| {
+            deserialize_redirect_table(IntoDeserializer::<value::Error>::into_deserializer(table))
+        };
+
+        assert!(parse("").unwrap().is_empty());
+        assert_eq!(
+            parse("\"/old-page\" = \"/new-page\"\n\"/search\" = \"/find?tags=a,b\"").unwrap(),
+            BTreeMap::from([
+                ("/old-page".to_owned(), "/new-page".to_owned()),
+                ("/search".to_owned(), "/find?tags=a,b".to_owned()),
+            ])
+        );
+        assert!(parse("/old-page=/new-page").is_err());
+    }
+}
@@ -0,0 +1,407 @@
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use std::{collections::BTreeMap, env, error::Error, fmt, fs, io};
+
+const CUSTOM_CONFIG_FILE: &str = "MoonZoonCustom.toml";
+
+// ------ custom ------
+
+/// Loads the app config from `MoonZoonCustom.toml` when the file exists,
+/// otherwise from env variables named the same way as mzoon names them
+/// (e.g. `[postgres] user = { name = "postgres" }` => `POSTGRES_USER_NAME`).
+///
+/// ```ignore
+/// pub static CUSTOM_CONFIG: Lazy<CustomConfig> = Lazy::new(config::custom_or_exit);
+///
+/// #[derive(Debug, Deserialize)]
+/// #[serde(crate = "serde")]
+/// pub struct CustomConfig {
+///     pub my_api: String,
+///     pub postgres: Postgres,
+/// }
+/// ```
+pub fn custom<T: DeserializeOwned>() -> Result<T, CustomConfigError> {
+    match fs::read_to_string(CUSTOM_CONFIG_FILE) {
+        Ok(toml) => toml::from_str::<toml::Value>(&toml)
+            .and_then(|value| stringify_datetimes(value).try_into())
+            .map_err(CustomConfigError::InvalidToml),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            let env_vars = env::vars().collect::<BTreeMap<_, _>>();
+            T::deserialize(EnvVars {
+                env_vars: &env_vars,
+                prefix: String::new(),
+            })
+            .map_err(CustomConfigError::InvalidEnvVars)
+        }
+        Err(error) => Err(CustomConfigError::CannotReadToml(error)),
+    }
+}
+
+// TOML datetimes are passed as strings to make them compatible with `chrono` types
+// and with the values loaded from env variables.
+fn stringify_datetimes(value: toml::Value) -> toml::Value {
+    match value {
+        toml::Value::Datetime(datetime) => toml::Value::String(datetime.to_string()),
+        toml::Value::Array(array) => {
+            toml::Value::Array(array.into_iter().map(stringify_datetimes).collect())
+        }
+        toml::Value::Table(table) => toml::Value::Table(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, stringify_datetimes(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Calls [`custom`] and exits the process with a readable error message on failure.
+pub fn custom_or_exit<T: DeserializeOwned>() -> T {
+    custom().unwrap_or_else(|error| {
+        eprintln!("Cannot load the custom config: {error}");
+        std::process::exit(1)
+    })
+}
+
+// ------ CustomConfigError ------
+
+#[derive(Debug)]
+pub enum CustomConfigError {
+    CannotReadToml(io::Error),
+    InvalidToml(toml::de::Error),
+    InvalidEnvVars(EnvVarsError),
+}
+
+impl fmt::Display for CustomConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CannotReadToml(error) => write!(f, "cannot read {CUSTOM_CONFIG_FILE}: {error}"),
+            Self::InvalidToml(error) => write!(f, "invalid {CUSTOM_CONFIG_FILE}: {error}"),
+            Self::InvalidEnvVars(error) => write!(f, "invalid env variables: {error}"),
+        }
+    }
+}
+
+impl Error for CustomConfigError {}
+
+// ------ EnvVarsError ------
+
+#[derive(Debug)]
+pub struct EnvVarsError {
+    message: String,
+    missing_field: Option<&'static str>,
+}
+
+impl fmt::Display for EnvVarsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for EnvVarsError {}
+
+impl de::Error for EnvVarsError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Self {
+            message: message.to_string(),
+            missing_field: None,
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self {
+            message: format!("missing field `{field}`"),
+            missing_field: Some(field),
+        }
+    }
+}
+
+// ------ EnvVars ------
+
+// Nested structs are resolved by field names, so `POSTGRES_USER_NAME`
+// may mean both `postgres.user.name` and `postgres.user_name`.
+struct EnvVars<'a> {
+    env_vars: &'a BTreeMap<String, String>,
+    prefix: String,
+}
+
+impl EnvVars<'_> {
+    fn contains(&self, name: &str) -> bool {
+        let nested_prefix = format!("{name}_");
+        self.env_vars
+            .keys()
+            .any(|key| key == name || key.starts_with(&nested_prefix))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for EnvVars<'_> {
+    type Error = EnvVarsError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom(format!(
+            "env variables prefixed with '{}' can be loaded only into a struct",
+            self.prefix
+        )))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.contains(self.prefix.trim_end_matches('_')) {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let prefix = self.prefix.clone();
+        visitor
+            .visit_map(Fields {
+                env_vars: self,
+                fields: fields.iter(),
+                current_name: None,
+            })
+            .map_err(|error| match error.missing_field {
+                Some(field) => de::Error::custom(format!(
+                    "missing env variable '{prefix}{}'",
+                    field.to_uppercase()
+                )),
+                None => error,
+            })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+struct Fields<'a> {
+    env_vars: EnvVars<'a>,
+    fields: std::slice::Iter<'static, &'static str>,
+    current_name: Option<String>,
+}
+
+impl<'de> MapAccess<'de> for Fields<'_> {
+    type Error = EnvVarsError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        for field in self.fields.by_ref() {
+            let name = format!("{}{}", self.env_vars.prefix, field.to_uppercase());
+            if self.env_vars.contains(&name) {
+                self.current_name = Some(name);
+                return seed.deserialize(field.into_deserializer()).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let name = self
+            .current_name
+            .take()
+            .ok_or_else(|| de::Error::custom("value requested before key"))?;
+        match self.env_vars.env_vars.get(&name) {
+            Some(value) => seed.deserialize(EnvValue { name: &name, value }),
+            None => seed.deserialize(EnvVars {
+                env_vars: self.env_vars.env_vars,
+                prefix: format!("{name}_"),
+            }),
+        }
+    }
+}
+
+// ------ EnvValue ------
+
+#[derive(Clone, Copy)]
+struct EnvValue<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+impl EnvValue<'_> {
+    fn parse<T>(&self) -> Result<T, EnvVarsError>
+    where
+        T: std::str::FromStr,
+        T::Err: fmt::Display,
+    {
+        self.value.trim().parse().map_err(|error| {
+            de::Error::custom(format!(
+                "invalid value '{}' of env variable '{}': {error}",
+                self.value, self.name
+            ))
+        })
+    }
+}
+
+impl<'de> IntoDeserializer<'de, EnvVarsError> for EnvValue<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for EnvValue<'_> {
+    type Error = EnvVarsError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.value)
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.value.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    // Arrays are joined with commas by mzoon.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let name = self.name;
+        let items = self
+            .value
+            .split(',')
+            .filter(|item| !item.is_empty())
+            .map(|item| EnvValue { name, value: item });
+        visitor.visit_seq(de::value::SeqDeserializer::new(items))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+// ====== ====== TESTS ====== ======
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct CustomConfig {
+        my_api: String,
+        ratio: f32,
+        favorite_languages: Vec<String>,
+        nickname: Option<String>,
+        postgres: Postgres,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Postgres {
+        user: PostgresUser,
+        port: u16,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct PostgresUser {
+        name: String,
+    }
+
+    fn from_env_vars<T: DeserializeOwned>(env_vars: &[(&str, &str)]) -> Result<T, EnvVarsError> {
+        let env_vars = env_vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        T::deserialize(EnvVars {
+            env_vars: &env_vars,
+            prefix: String::new(),
+        })
+    }
+
+    #[test]
+    fn test_nested_env_vars() {
+        let config = from_env_vars::<CustomConfig>(&[
+            ("MY_API", "example.com/api"),
+            ("RATIO", "2.5"),
+            ("FAVORITE_LANGUAGES", "Rust,Gleam"),
+            ("POSTGRES_USER_NAME", "postgres"),
+            ("POSTGRES_PORT", "5432"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            config,
+            CustomConfig {
+                my_api: "example.com/api".to_owned(),
+                ratio: 2.5,
+                favorite_languages: vec!["Rust".to_owned(), "Gleam".to_owned()],
+                nickname: None,
+                postgres: Postgres {
+                    user: PostgresUser {
+                        name: "postgres".to_owned()
+                    },
+                    port: 5432,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_env_var_errors() {
+        let error = from_env_vars::<Postgres>(&[("USER_NAME", "postgres"), ("PORT", "high")])
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("invalid value 'high' of env variable 'PORT'"));
+
+        let error = from_env_vars::<Postgres>(&[("PORT", "5432"), ("USER_ID", "1")])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "missing env variable 'USER_NAME'");
+    }
+}
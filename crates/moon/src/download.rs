@@ -0,0 +1,133 @@
+use actix_files::NamedFile;
+use actix_web::http::header::{
+    CacheControl, CacheDirective, ContentDisposition, DispositionParam, DispositionType,
+};
+use actix_web::{web, Error, HttpRequest, HttpResponse, Responder};
+use chashmap::CHashMap;
+use once_cell::sync::Lazy;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+static DOWNLOADS: Lazy<CHashMap<String, Download>> = Lazy::new(CHashMap::new);
+
+// ------ Download ------
+
+/// One-time download URL for a binary file.
+///
+/// ```ignore
+/// let url = Download::new("reports/2022.pdf").file_name("report.pdf").url();
+/// connection.send_down_msg(&DownMsg::ReportReady { url }, cor_id).await;
+/// ```
+///
+/// The URL contains an unguessable random token. It's valid only until the first successful
+/// download or until it expires.
+pub struct Download {
+    path: PathBuf,
+    file_name: Option<String>,
+    content_type: Option<mime::Mime>,
+    expires_in: Duration,
+    created_at: Instant,
+}
+
+impl Download {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file_name: None,
+            content_type: None,
+            expires_in: Duration::from_secs(5 * 60),
+            created_at: Instant::now(),
+        }
+    }
+
+    /// The file name offered by the browser. The name of the file on the disk is used by default.
+    pub fn file_name(mut self, file_name: impl ToString) -> Self {
+        self.file_name = Some(file_name.to_string());
+        self
+    }
+
+    /// The content type is guessed from the file extension by default.
+    pub fn content_type(mut self, content_type: mime::Mime) -> Self {
+        self.content_type = Some(content_type);
+        self
+    }
+
+    pub fn expires_in(mut self, duration: Duration) -> Self {
+        self.expires_in = duration;
+        self
+    }
+
+    /// Registers the download and returns its URL (`/_api/download/{token}`).
+    pub fn url(self) -> String {
+        remove_expired_downloads();
+        let token = Uuid::new_v4().simple().to_string();
+        let url = format!("/_api/download/{token}");
+        DOWNLOADS.insert(token, self);
+        url
+    }
+
+    fn is_expired(&self) -> bool {
+        self.created_at.elapsed() > self.expires_in
+    }
+}
+
+fn remove_expired_downloads() {
+    DOWNLOADS.retain(|_, download| !download.is_expired());
+}
+
+// ------ download_responder ------
+
+pub(crate) async fn download_responder(
+    req: HttpRequest,
+    token: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let (path, file_name, content_type) = match DOWNLOADS.get(token.as_str()) {
+        Some(download) if !download.is_expired() => {
+            let file_name = download.file_name.clone().or_else(|| {
+                download
+                    .path
+                    .file_name()
+                    .map(|file_name| file_name.to_string_lossy().into_owned())
+            });
+            let content_type = download
+                .content_type
+                .clone()
+                .unwrap_or_else(|| mime_guess::from_path(&download.path).first_or_octet_stream());
+            (download.path.clone(), file_name, content_type)
+        }
+        _ => return Ok(download_not_found()),
+    };
+
+    // The token is consumed only after the file has been opened
+    // so a failed attempt doesn't invalidate the URL.
+    let named_file = NamedFile::open_async(&path).await?;
+    if DOWNLOADS.remove(token.as_str()).is_none() {
+        // The download has been served to a concurrent request in the meantime.
+        return Ok(download_not_found());
+    }
+
+    let mut named_file = named_file
+        .set_content_type(content_type)
+        .use_etag(false)
+        .use_last_modified(false);
+
+    if let Some(file_name) = file_name {
+        named_file = named_file.set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        });
+    }
+
+    Ok(named_file
+        .customize()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .respond_to(&req)
+        .map_into_boxed_body())
+}
+
+fn download_not_found() -> HttpResponse {
+    HttpResponse::NotFound()
+        .reason("Download Not Found")
+        .finish()
+}
@@ -7,15 +7,24 @@ where
     const ENTITY_NAME: &'static str;
     const ENV_PREFIX: &'static str = "";
 
+    fn try_from_env_vars() -> Result<Self, envy::Error> {
+        envy::prefixed(Self::ENV_PREFIX).from_env()
+    }
+
+    /// Exits the process with a readable error message when the env variables are invalid.
     fn from_env_vars() -> Self {
-        envy::prefixed(Self::ENV_PREFIX)
-            .from_env()
-            .unwrap_or_else(|error| {
-                panic!(
-                    "cannot load {} from env variables: {}",
-                    Self::ENTITY_NAME,
-                    error
-                )
-            })
+        Self::try_from_env_vars().unwrap_or_else(|error| {
+            eprintln!(
+                "Cannot load {} from env variables{}: {}",
+                Self::ENTITY_NAME,
+                if Self::ENV_PREFIX.is_empty() {
+                    String::new()
+                } else {
+                    format!(" prefixed with '{}'", Self::ENV_PREFIX)
+                },
+                error
+            );
+            std::process::exit(1)
+        })
     }
 }
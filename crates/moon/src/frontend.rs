@@ -1,8 +1,13 @@
 use crate::CONFIG;
 use lang::Lang;
+use once_cell::sync::OnceCell;
 use std::borrow::Cow;
 use tokio::fs;
 
+// The frontend build id read on start. It's compared with the `X-MoonZoon-Version` header
+// and the SSE `version` event, so it mustn't change while Moon is running.
+static VERSION: OnceCell<u128> = OnceCell::new();
+
 pub struct Frontend {
     pub(crate) lang: Option<Lang>,
     pub(crate) index_by_robots: bool,
@@ -34,6 +39,14 @@ impl Frontend {
             .unwrap_or_default()
     }
 
+    pub(crate) fn set_version(version: u128) {
+        let _ = VERSION.set(version);
+    }
+
+    pub(crate) fn version() -> u128 {
+        VERSION.get().copied().unwrap_or_default()
+    }
+
     pub fn new() -> Self {
         Self::default()
     }
@@ -84,6 +97,20 @@ impl Frontend {
             Cow::from("")
         };
 
+        let meta_public_url = if CONFIG.fingerprinted_public {
+            Cow::from(format!(
+                r#"<meta name="moonzoon-public-url" content="/_api/public_{}/">"#,
+                Self::build_id().await
+            ))
+        } else {
+            Cow::from("")
+        };
+
+        let meta_version = format!(
+            r#"<meta name="moonzoon-version" content="{}">"#,
+            Self::version()
+        );
+
         let meta_robots = if index_by_robots {
             ""
         } else {
@@ -131,6 +158,8 @@ impl Frontend {
           <meta charset="utf-8" />
           <meta name="viewport" content="width=device-width, initial-scale=1, shrink-to-fit=no" />
           {meta_robots}
+          {meta_public_url}
+          {meta_version}
           <title>{title}</title>
           <link rel="preload" href="/_api/pkg/frontend_bg{cache_busting_string}.wasm" as="fetch" type="application/wasm" crossorigin>
           <link rel="modulepreload" href="/_api/pkg/frontend{cache_busting_string}.js" crossorigin>
@@ -0,0 +1,378 @@
+//! Scheduled and recurring background jobs.
+//!
+//! ```ignore
+//! jobs::register(
+//!     Job::every(Duration::from_secs(60), expire_invoices)
+//!         .name("expire_invoices")
+//!         .jitter(Duration::from_secs(5)),
+//! );
+//! jobs::register(Job::cron("0 8 * * MON-FRI", send_digests)?.name("send_digests"));
+//! ```
+//!
+//! Registered jobs are started together with the Moon server
+//! and cancelled when the server has been gracefully stopped.
+
+use actix_web::rt::{self, task::JoinHandle};
+use futures::{
+    future::LocalBoxFuture,
+    stream::{FuturesUnordered, StreamExt},
+    FutureExt,
+};
+use moonlight::chrono::Utc;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::fmt::Display;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+mod cron;
+
+const DEFAULT_MAX_OVERLAPPING_RUNS: usize = 16;
+
+pub use cron::{CronError, CronSchedule};
+
+static JOBS: Lazy<Mutex<Jobs>> = Lazy::new(Mutex::default);
+
+#[derive(Default)]
+struct Jobs {
+    started: bool,
+    pending: Vec<Job>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+// ------ register ------
+
+/// Starts the job immediately when the Moon server is already running,
+/// otherwise the job waits for the server start.
+pub fn register(job: Job) {
+    let mut jobs = JOBS.lock();
+    if jobs.started {
+        let handle = job.spawn();
+        jobs.handles.push(handle);
+    } else {
+        jobs.pending.push(job);
+    }
+}
+
+pub(crate) fn start_registered() {
+    let mut jobs = JOBS.lock();
+    jobs.started = true;
+    let pending = std::mem::take(&mut jobs.pending);
+    let handles = pending.into_iter().map(Job::spawn).collect::<Vec<_>>();
+    jobs.handles.extend(handles);
+}
+
+pub(crate) fn cancel_all() {
+    let mut jobs = JOBS.lock();
+    jobs.started = false;
+    for handle in jobs.handles.drain(..) {
+        handle.abort();
+    }
+}
+
+// ------ JobOutput ------
+
+pub trait JobOutput {
+    fn into_result(self) -> Result<(), String>;
+}
+
+impl JobOutput for () {
+    fn into_result(self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl<E: Display> JobOutput for Result<(), E> {
+    fn into_result(self) -> Result<(), String> {
+        self.map_err(|error| error.to_string())
+    }
+}
+
+// ------ Schedule ------
+
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Interval(Duration),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    fn delay_to_next_run(&self) -> Option<Duration> {
+        match self {
+            Self::Interval(interval) => Some(*interval),
+            Self::Cron(cron_schedule) => {
+                let now = Utc::now();
+                let next_run = cron_schedule.next_after(now)?;
+                (next_run - now).to_std().ok()
+            }
+        }
+    }
+}
+
+// ------ Job ------
+
+type JobFn = Arc<dyn Fn() -> LocalBoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+pub struct Job {
+    name: String,
+    schedule: Schedule,
+    jitter: Duration,
+    allow_overlap: bool,
+    max_overlapping_runs: usize,
+    run_on_start: bool,
+    job_fn: JobFn,
+}
+
+impl Job {
+    pub fn new<F, FO>(schedule: Schedule, job_fn: F) -> Self
+    where
+        F: Fn() -> FO + Send + Sync + 'static,
+        FO: Future + 'static,
+        FO::Output: JobOutput,
+    {
+        Self {
+            name: String::from("unnamed"),
+            schedule,
+            jitter: Duration::ZERO,
+            allow_overlap: false,
+            max_overlapping_runs: DEFAULT_MAX_OVERLAPPING_RUNS,
+            run_on_start: false,
+            job_fn: Arc::new(move || {
+                let job = job_fn();
+                Box::pin(async move { job.await.into_result() })
+            }),
+        }
+    }
+
+    pub fn every<F, FO>(interval: Duration, job_fn: F) -> Self
+    where
+        F: Fn() -> FO + Send + Sync + 'static,
+        FO: Future + 'static,
+        FO::Output: JobOutput,
+    {
+        Self::new(Schedule::Interval(interval), job_fn)
+    }
+
+    /// See [`CronSchedule`] for the supported expression syntax. Times are in UTC.
+    pub fn cron<F, FO>(expression: &str, job_fn: F) -> Result<Self, CronError>
+    where
+        F: Fn() -> FO + Send + Sync + 'static,
+        FO: Future + 'static,
+        FO::Output: JobOutput,
+    {
+        Ok(Self::new(Schedule::Cron(expression.parse()?), job_fn))
+    }
+
+    pub fn name(mut self, name: impl ToString) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Adds a random delay in the range `0..jitter` before every run.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// By default, the next run is scheduled only after the previous one has finished.
+    pub fn allow_overlap(mut self, allow: bool) -> Self {
+        self.allow_overlap = allow;
+        self
+    }
+
+    /// Runs are skipped while `max` overlapping runs are in progress. The default is 16.
+    pub fn max_overlapping_runs(mut self, max: usize) -> Self {
+        self.max_overlapping_runs = max.max(1);
+        self
+    }
+
+    pub fn run_on_start(mut self, run: bool) -> Self {
+        self.run_on_start = run;
+        self
+    }
+
+    fn random_jitter(&self) -> Duration {
+        let jitter_millis = self.jitter.as_millis();
+        if jitter_millis == 0 {
+            return Duration::ZERO;
+        }
+        let random_millis = Uuid::new_v4().as_u128() % jitter_millis;
+        Duration::from_millis(random_millis as u64)
+    }
+
+    fn spawn(self) -> JoinHandle<()> {
+        rt::spawn(async move {
+            // Overlapping runs are owned by the job task,
+            // so they are cancelled together with the job.
+            let mut runs = FuturesUnordered::new();
+            let mut first_run = self.run_on_start;
+            loop {
+                if !first_run {
+                    let delay = match self.schedule.delay_to_next_run() {
+                        Some(delay) => delay,
+                        None => {
+                            log::warn!("Job '{}' has no next run, stopping", self.name);
+                            break;
+                        }
+                    };
+                    let next_run = rt::time::sleep(delay + self.random_jitter());
+                    futures::pin_mut!(next_run);
+                    loop {
+                        tokio::select! {
+                            () = &mut next_run => break,
+                            Some(()) = runs.next(), if !runs.is_empty() => {}
+                        }
+                    }
+                }
+                first_run = false;
+
+                let run = run(self.name.clone(), Arc::clone(&self.job_fn));
+                if !self.allow_overlap {
+                    // The next run is scheduled after the current one has finished,
+                    // so runs never overlap.
+                    run.await;
+                } else if runs.len() < self.max_overlapping_runs {
+                    runs.push(run.boxed_local());
+                } else {
+                    log::warn!(
+                        "Job '{}' skipped, {} runs are still in progress",
+                        self.name,
+                        runs.len()
+                    );
+                }
+            }
+            while runs.next().await.is_some() {}
+        })
+    }
+}
+
+async fn run(name: String, job_fn: JobFn) {
+    let started_at = Instant::now();
+    log::debug!("Job '{name}' started");
+    // The job may panic also before it returns the future.
+    let job = AssertUnwindSafe(async move { job_fn().await }).catch_unwind();
+    match job.await {
+        Ok(Ok(())) => log::info!("Job '{name}' finished in {:?}", started_at.elapsed()),
+        Ok(Err(error)) => log::error!(
+            "Job '{name}' failed after {:?}: {error}",
+            started_at.elapsed()
+        ),
+        Err(panic) => log::error!(
+            "Job '{name}' panicked after {:?}: {}",
+            started_at.elapsed(),
+            crate::panic_message(panic.as_ref())
+        ),
+    }
+}
+
+// ====== ====== TESTS ====== ======
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::rt as actix_rt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Counts running job runs, the count is decremented also when a run is cancelled.
+    #[derive(Default)]
+    struct RunCounter {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+        finished: AtomicUsize,
+    }
+
+    struct RunGuard(Arc<RunCounter>);
+
+    impl RunGuard {
+        fn new(counter: &Arc<RunCounter>) -> Self {
+            let running = counter.running.fetch_add(1, Ordering::SeqCst) + 1;
+            counter.max_running.fetch_max(running, Ordering::SeqCst);
+            Self(Arc::clone(counter))
+        }
+    }
+
+    impl Drop for RunGuard {
+        fn drop(&mut self) {
+            self.0.running.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn counting_job(counter: &Arc<RunCounter>, duration: Duration) -> Job {
+        let counter = Arc::clone(counter);
+        Job::every(Duration::from_millis(5), move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                let _guard = RunGuard::new(&counter);
+                rt::time::sleep(duration).await;
+                counter.finished.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_interval() {
+        let counter = Arc::default();
+        let handle = counting_job(&counter, Duration::ZERO).spawn();
+
+        rt::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        assert!(counter.finished.load(Ordering::SeqCst) >= 2);
+    }
+
+    #[actix_rt::test]
+    async fn test_no_overlap() {
+        let counter = Arc::default();
+        let handle = counting_job(&counter, Duration::from_millis(20)).spawn();
+
+        rt::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        assert!(counter.finished.load(Ordering::SeqCst) >= 2);
+        assert_eq!(counter.max_running.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_overlapping_runs_are_bounded_and_cancelled() {
+        let counter = Arc::default();
+        let handle = counting_job(&counter, Duration::from_secs(60))
+            .allow_overlap(true)
+            .max_overlapping_runs(3)
+            .spawn();
+
+        rt::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(counter.running.load(Ordering::SeqCst), 3);
+
+        handle.abort();
+        assert!(handle.await.unwrap_err().is_cancelled());
+
+        assert_eq!(counter.running.load(Ordering::SeqCst), 0);
+        assert_eq!(counter.max_running.load(Ordering::SeqCst), 3);
+        assert_eq!(counter.finished.load(Ordering::SeqCst), 0);
+    }
+
+    #[actix_rt::test]
+    async fn test_panic_doesnt_stop_job() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let handle = Job::every(Duration::from_millis(5), {
+            let runs = Arc::clone(&runs);
+            move || {
+                let run = runs.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if run == 0 {
+                        panic!("first run failed");
+                    }
+                }
+            }
+        })
+        .spawn();
+
+        rt::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        assert!(runs.load(Ordering::SeqCst) >= 2);
+    }
+}
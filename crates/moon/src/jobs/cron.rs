@@ -0,0 +1,268 @@
+use moonlight::chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, NaiveDate, NaiveDateTime, Timelike, Utc,
+};
+use std::{error::Error, fmt, str::FromStr};
+
+// How many times we can skip to the next month / day / hour / minute
+// before we give up searching the next run (e.g. for `0 0 30 2 *`).
+const MAX_SEARCH_STEPS: usize = 100_000;
+
+// ------ CronSchedule ------
+
+/// Standard 5-field cron expression: `minute hour day_of_month month day_of_week`.
+///
+/// Every field supports `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps (`*/10`, `0-30/5`).
+/// Months and days of the week may be written as names (`JAN`, `MON-FRI`),
+/// Sunday is `0` or `7`. Macros `@hourly`, `@daily`, `@weekly`, `@monthly`
+/// and `@yearly` are supported as well.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl CronSchedule {
+    /// Returns the first matching time (with zero seconds) strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let after = after.naive_utc();
+        let mut time =
+            after.date().and_hms_opt(after.hour(), after.minute(), 0)? + ChronoDuration::minutes(1);
+
+        for _ in 0..MAX_SEARCH_STEPS {
+            if !contains(self.months, time.month()) {
+                time = first_day_of_next_month(time)?;
+                continue;
+            }
+            if !self.matches_day(time.date()) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !contains(self.hours, time.hour()) {
+                time = time.date().and_hms_opt(time.hour(), 0, 0)? + ChronoDuration::hours(1);
+                continue;
+            }
+            if !contains(self.minutes, time.minute()) {
+                time += ChronoDuration::minutes(1);
+                continue;
+            }
+            return Some(DateTime::from_utc(time, Utc));
+        }
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = contains(self.days_of_month, date.day());
+        let day_of_week = contains(self.days_of_week, date.weekday().num_days_from_sunday());
+        // Standard cron behavior: when both day fields are restricted, either of them may match.
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = CronError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let expression = match expression.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            expression => expression,
+        };
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        let (minutes, hours, days_of_month, months, days_of_week) = match fields[..] {
+            [minutes, hours, days_of_month, months, days_of_week] => {
+                (minutes, hours, days_of_month, months, days_of_week)
+            }
+            _ => return Err(CronError::InvalidFieldCount(fields.len())),
+        };
+
+        let mut days_of_week_bits = parse_field(days_of_week, 0, 7, &DAY_NAMES)?;
+        // Both `0` and `7` mean Sunday.
+        if contains(days_of_week_bits, 7) {
+            days_of_week_bits |= 1;
+        }
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59, &[])?,
+            hours: parse_field(hours, 0, 23, &[])?,
+            days_of_month: parse_field(days_of_month, 1, 31, &[])?,
+            months: parse_field(months, 1, 12, &MONTH_NAMES)?,
+            days_of_week: days_of_week_bits,
+            any_day_of_month: days_of_month == "*",
+            any_day_of_week: days_of_week == "*",
+        })
+    }
+}
+
+// ------ helpers ------
+
+const MONTH_NAMES: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const DAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+fn contains(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+fn first_day_of_next_month(time: NaiveDateTime) -> Option<NaiveDateTime> {
+    let (year, month) = if time.month() == 12 {
+        (time.year() + 1, 1)
+    } else {
+        (time.year(), time.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)
+}
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, CronError> {
+    let invalid_field = || CronError::InvalidField(field.to_owned());
+
+    let parse_value = |value: &str| -> Result<u32, CronError> {
+        let first_name_value = if names.len() == 12 { 1 } else { 0 };
+        let value = names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(value))
+            .map(|position| Ok(position as u32 + first_name_value))
+            .unwrap_or_else(|| value.parse().map_err(|_| invalid_field()))?;
+        if value < min || value > max {
+            return Err(invalid_field());
+        }
+        Ok(value)
+    };
+
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid_field())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid_field());
+        }
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (parse_value(start)?, parse_value(end)?),
+                // `5/15` means "every 15 starting at 5".
+                None if step > 1 => (parse_value(range)?, max),
+                None => {
+                    let value = parse_value(range)?;
+                    (value, value)
+                }
+            },
+        };
+        if start > end {
+            return Err(invalid_field());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+// ------ CronError ------
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CronError {
+    InvalidFieldCount(usize),
+    InvalidField(String),
+}
+
+impl fmt::Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFieldCount(count) => {
+                write!(f, "cron expression has to have 5 fields, found {count}")
+            }
+            Self::InvalidField(field) => {
+                write!(f, "invalid cron expression field '{field}'")
+            }
+        }
+    }
+}
+
+impl Error for CronError {}
+
+// ====== ====== TESTS ====== ======
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use moonlight::chrono::TimeZone;
+
+    fn next_after(expression: &str, after: DateTime<Utc>) -> DateTime<Utc> {
+        expression
+            .parse::<CronSchedule>()
+            .unwrap()
+            .next_after(after)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_every_minute() {
+        assert_eq!(
+            next_after("* * * * *", Utc.ymd(2022, 1, 1).and_hms(10, 15, 30)),
+            Utc.ymd(2022, 1, 1).and_hms(10, 16, 0)
+        );
+    }
+
+    #[test]
+    fn test_steps_and_ranges() {
+        assert_eq!(
+            next_after("*/15 9-17 * * *", Utc.ymd(2022, 1, 1).and_hms(17, 50, 0)),
+            Utc.ymd(2022, 1, 2).and_hms(9, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_days_of_week() {
+        // 2022-01-01 is Saturday.
+        assert_eq!(
+            next_after("0 8 * * MON-FRI", Utc.ymd(2022, 1, 1).and_hms(12, 0, 0)),
+            Utc.ymd(2022, 1, 3).and_hms(8, 0, 0)
+        );
+        assert_eq!(
+            next_after("0 0 * * 7", Utc.ymd(2022, 1, 1).and_hms(12, 0, 0)),
+            Utc.ymd(2022, 1, 2).and_hms(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_macros_and_months() {
+        assert_eq!(
+            next_after("@monthly", Utc.ymd(2022, 12, 15).and_hms(0, 0, 0)),
+            Utc.ymd(2023, 1, 1).and_hms(0, 0, 0)
+        );
+        assert_eq!(
+            next_after("0 0 29 FEB *", Utc.ymd(2022, 3, 1).and_hms(0, 0, 0)),
+            Utc.ymd(2024, 2, 29).and_hms(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert_eq!(
+            "* * * *".parse::<CronSchedule>(),
+            Err(CronError::InvalidFieldCount(4))
+        );
+        assert_eq!(
+            "60 * * * *".parse::<CronSchedule>(),
+            Err(CronError::InvalidField("60".to_owned()))
+        );
+        assert!("0 0 30 2 *"
+            .parse::<CronSchedule>()
+            .unwrap()
+            .next_after(Utc::now())
+            .is_none());
+    }
+}
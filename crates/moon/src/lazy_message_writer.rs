@@ -4,6 +4,7 @@ use qrcode::{render::unicode as QrUnicode, QrCode};
 use std::{
     io::{self, stdout, Write},
     net::SocketAddr,
+    path::Path,
 };
 
 pub struct LazyMessageWriter(Vec<u8>);
@@ -17,15 +18,36 @@ impl LazyMessageWriter {
         stdout().write_all(&self.0)
     }
 
-    pub fn server_is_running(&mut self, address: &SocketAddr, config: &Config) -> io::Result<()> {
+    pub fn server_is_running(
+        &mut self,
+        addresses: &[SocketAddr],
+        config: &Config,
+    ) -> io::Result<()> {
         let protocol = if config.https { "https" } else { "http" };
-        let port = config.port;
-        writeln!(
-            &mut self.0,
-            "Server is running on {protocol}://{address} [{protocol}://localhost:{port}]",
-        )?;
-        if let Ok(ip) = local_ip() {
-            let url = format!("{protocol}://{ip}:{port}");
+        for address in addresses {
+            let ip = address.ip();
+            if ip.is_unspecified() || ip.is_loopback() {
+                let port = address.port();
+                writeln!(
+                    &mut self.0,
+                    "Server is running on {protocol}://{address} [{protocol}://localhost:{port}]",
+                )?;
+            } else {
+                writeln!(&mut self.0, "Server is running on {protocol}://{address}")?;
+            }
+        }
+        // The server is reachable from the local network only
+        // if it listens on all interfaces or directly on the local IP.
+        let local_address = local_ip().ok().and_then(|local_ip| {
+            addresses.iter().find_map(|address| {
+                let ip = address.ip();
+                let reachable =
+                    (ip.is_unspecified() && ip.is_ipv4() == local_ip.is_ipv4()) || ip == local_ip;
+                reachable.then(|| SocketAddr::new(local_ip, address.port()))
+            })
+        });
+        if let Some(local_address) = local_address {
+            let url = format!("{protocol}://{local_address}");
 
             let qr_code = QrCode::new(&url)
                 .expect("failed to create a QR code with the server url")
@@ -41,11 +63,22 @@ impl LazyMessageWriter {
         Ok(())
     }
 
-    pub fn redirect_from(&mut self, address: &SocketAddr, config: &Config) -> io::Result<()> {
-        let port = config.port;
+    pub fn unix_socket(&mut self, path: &Path) -> io::Result<()> {
         writeln!(
             &mut self.0,
-            "Redirect from http://{address} [http://localhost:{port}]",
+            "Server is running on the Unix socket {}",
+            path.display()
         )
     }
+
+    pub fn redirect_from(&mut self, addresses: &[SocketAddr]) -> io::Result<()> {
+        for address in addresses {
+            let port = address.port();
+            writeln!(
+                &mut self.0,
+                "Redirect from http://{address} [http://localhost:{port}]",
+            )?;
+        }
+        Ok(())
+    }
 }
@@ -1,5 +1,5 @@
 use actix_cors::Cors;
-use actix_files::{Files, NamedFile};
+use actix_files::NamedFile;
 use actix_http::{
     header::{self, HeaderMap},
     ContentEncoding,
@@ -10,20 +10,20 @@ use actix_web::{
     dev::{ServiceFactory, ServiceRequest, ServiceResponse},
     error::{self, Error},
     http::StatusCode,
-    middleware::{Compat, Condition, ErrorHandlers, Logger},
-    web, App, HttpRequest, HttpResponse, HttpServer, Responder, Result,
+    middleware::{Compat, Condition, DefaultHeaders, ErrorHandlers, Logger},
+    rt, web, App, HttpRequest, HttpResponse, HttpServer, Responder, Result, Scope,
 };
-use rustls::{Certificate, PrivateKey, ServerConfig as RustlsServerConfig};
-use rustls_pemfile::{certs, pkcs8_private_keys};
-use std::fs::File;
-use std::io::{self, BufReader};
+use std::any::Any;
 use std::net::SocketAddr;
 use std::ops::Deref;
+use std::panic::AssertUnwindSafe;
+use std::path::{Component, Path};
 use std::sync::Arc;
 use std::{collections::BTreeSet, future::Future};
+use std::{fmt, io};
 use tokio::fs;
 
-use futures::StreamExt;
+use futures::{future::LocalBoxFuture, FutureExt, StreamExt};
 
 pub use actix_cors;
 pub use actix_files;
@@ -50,41 +50,56 @@ pub use trait_set::trait_set;
 pub use uuid;
 
 mod actor;
+mod admin;
 pub mod config;
+mod download;
 pub mod error_handler;
 mod from_env_vars;
 mod frontend;
+pub mod jobs;
 mod lazy_message_writer;
 mod not;
 mod redirect;
 mod sse;
+pub mod testing;
+mod tls;
 mod up_msg_request;
+pub mod upload;
 
 use config::CONFIG;
 use lazy_message_writer::LazyMessageWriter;
 use sse::{ShareableSSE, ShareableSSEMethods, SSE};
 
 pub use actor::{
+    instance_counts, instance_created, instance_removed,
     sessions::{self, SessionActor},
-    ActorId, ActorInstance, Index, PVar,
+    ActorId, ActorInstance, Index, IndexNotifier, PVar,
 };
+pub use download::Download;
 pub use from_env_vars::FromEnvVars;
 pub use frontend::Frontend;
 pub use not::not;
-pub use redirect::Redirect;
+pub use redirect::{Redirect, TrailingSlash};
+pub use sse::{OverflowPolicy, SSEMetrics};
 pub use up_msg_request::UpMsgRequest;
 
 // @TODO make it configurable
 // const MAX_UP_MSG_BYTES: usize = 2 * 1_048_576;
 const MAX_UP_MSG_BYTES: usize = usize::MAX;
 
+// Compared with the version in the frontend to detect outdated browser tabs.
+const VERSION_HEADER: &str = "X-MoonZoon-Version";
+
 #[derive(Copy, Clone)]
 struct SharedData {
     backend_build_id: u128,
     frontend_build_id: u128,
     cache_busting: bool,
     compressed_pkg: bool,
+    compressed_public: bool,
     pkg_path: &'static str,
+    public_path: &'static str,
+    compressed_public_path: &'static str,
 }
 
 #[derive(Clone)]
@@ -137,29 +152,24 @@ where
     UMsg: 'static + DeserializeOwned,
 {
     let app = || {
-        let redirect = Redirect::new()
-            .http_to_https(CONFIG.https)
-            .port(CONFIG.redirect.port, CONFIG.port);
+        let redirect = CONFIG.redirect.permanent.iter().fold(
+            Redirect::new()
+                .port_redirect(CONFIG.redirect.enabled)
+                .http_to_https(CONFIG.https)
+                .port(CONFIG.redirect.port, CONFIG.port)
+                .canonical_host(CONFIG.redirect.canonical_host.clone())
+                .trailing_slash(CONFIG.redirect.trailing_slash),
+            |redirect, (from, to)| redirect.permanent(from, to),
+        );
 
         App::new()
             .wrap(Condition::new(
-                CONFIG.redirect.enabled,
+                CONFIG.redirect.enabled || CONFIG.redirect.has_rules(),
                 Compat::new(redirect),
             ))
             // https://docs.rs/actix-web/4.0.0-beta.8/actix_web/middleware/struct.Logger.html
             .wrap(Logger::new(r#""%r" %s %b "%{Referer}i" %T"#))
-            .wrap(Cors::default().allowed_origin_fn(move |origin, _| {
-                if CONFIG.cors.origins.contains("*") {
-                    return true;
-                }
-                let origin = match origin.to_str() {
-                    Ok(origin) => origin,
-                    // Browsers should always send a valid Origin.
-                    // We don't care about invalid Origin sent from non-browser clients.
-                    Err(_) => return false,
-                };
-                CONFIG.cors.origins.contains(origin)
-            }))
+            .wrap(cors())
             .wrap(
                 ErrorHandlers::new()
                     .handler(
@@ -172,6 +182,41 @@ where
     start_with_app(frontend, up_msg_handler, app, service_config).await
 }
 
+fn cors() -> Cors {
+    let config = &CONFIG.cors;
+
+    let mut cors = Cors::default().allowed_origin_fn(|origin, _| {
+        if CONFIG.cors.origins.contains("*") {
+            return true;
+        }
+        let origin = match origin.to_str() {
+            Ok(origin) => origin,
+            // Browsers should always send a valid Origin.
+            // We don't care about invalid Origin sent from non-browser clients.
+            Err(_) => return false,
+        };
+        CONFIG.cors.origins.contains(origin)
+    });
+
+    cors = if config.allowed_headers.contains("*") {
+        cors.allow_any_header()
+    } else {
+        cors.allowed_headers(config.allowed_headers.iter().map(AsRef::<str>::as_ref))
+    };
+    // zoon compares the version also with a backend on another origin.
+    cors = cors.expose_headers(
+        config
+            .exposed_headers
+            .iter()
+            .map(AsRef::<str>::as_ref)
+            .chain([VERSION_HEADER]),
+    );
+    if config.credentials {
+        cors = cors.supports_credentials();
+    }
+    cors.max_age(config.max_age)
+}
+
 pub async fn start_with_app<FRB, FRBO, UPH, UPHO, UMsg, AT, AB, ABE>(
     frontend: FRB,
     up_msg_handler: UPH,
@@ -207,11 +252,16 @@ where
         frontend_build_id: Frontend::build_id().await,
         cache_busting: CONFIG.cache_busting,
         compressed_pkg: CONFIG.compressed_pkg,
+        compressed_public: CONFIG.compressed_public,
         pkg_path: "frontend/pkg",
+        public_path: "public",
+        compressed_public_path: "frontend/pkg/public",
     };
+    // `mzoon start` rebuilds the frontend without restarting Moon,
+    // so the build id in `frontend/pkg` isn't used as the version after start.
+    Frontend::set_version(shared_data.frontend_build_id);
     let reload_sse = ReloadSSE(SSE::start());
     let message_sse = MessageSSE(SSE::start());
-    let address = SocketAddr::from(([0, 0, 0, 0], CONFIG.port));
 
     let mut lazy_message_writer = LazyMessageWriter::new();
 
@@ -235,51 +285,64 @@ where
             .app_data(data_reload_sse.clone())
             .app_data(data_message_sse.clone())
             .configure(service_config.clone())
-            .service(
-                Files::new("_api/public", "public").default_handler(web::to(|| async {
-                    HttpResponse::NotFound().reason("File Not Found").finish()
-                })),
-            )
-            .service(
-                web::scope("_api")
-                    .route(
-                        "up_msg_handler",
-                        web::post().to(up_msg_handler_responder::<UPH, UPHO, UMsg>),
-                    )
-                    .route("reload", web::post().to(reload_responder))
-                    .route("pkg/{file:.*}", web::get().to(pkg_responder))
-                    .route(
-                        "message_sse/{session_id}",
-                        web::get().to(message_sse_responder),
-                    )
-                    .route("reload_sse", web::get().to(reload_sse_responder))
-                    .route("ping", web::to(|| async { "pong" }))
-                    .route(
-                        "{path:.*}",
-                        web::to(|| async {
-                            HttpResponse::NotFound().reason("API Not Found").finish()
-                        }),
-                    ),
-            )
+            .service(api_scope::<UPH, UPHO, UMsg>())
             .default_service(web::get().to(frontend_responder::<FRB, FRBO>))
+            .wrap(version_header())
     });
 
     // ------ Bind ------
 
-    server = if CONFIG.https {
-        server.bind_rustls(address, rustls_server_config()?)?
-    } else {
-        server.bind(address)?
-    };
-    lazy_message_writer.server_is_running(&address, &CONFIG)?;
-
-    server = if CONFIG.redirect.enabled {
-        let address = SocketAddr::from(([0, 0, 0, 0], CONFIG.redirect.port));
-        lazy_message_writer.redirect_from(&address, &CONFIG)?;
-        server.bind(address)?
+    if let Some(unix_socket) = &CONFIG.unix_socket {
+        #[cfg(unix)]
+        {
+            remove_stale_unix_socket(unix_socket)?;
+            server = server.bind_uds(unix_socket)?;
+            lazy_message_writer.unix_socket(unix_socket)?;
+        }
+        #[cfg(not(unix))]
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "UNIX_SOCKET is supported only on Unix systems",
+        ))?
     } else {
-        server
-    };
+        if CONFIG.bind_address.is_empty() {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "BIND_ADDRESS doesn't contain any address",
+            ))?
+        }
+        let addresses = || {
+            CONFIG
+                .bind_address
+                .iter()
+                .map(|ip| SocketAddr::new(*ip, CONFIG.port))
+        };
+        if CONFIG.https {
+            let rustls_server_config = tls::rustls_server_config(&CONFIG.tls)?;
+            for address in addresses() {
+                server = server.bind_rustls(address, rustls_server_config.clone())?;
+            }
+        } else {
+            for address in addresses() {
+                server = server.bind(address)?;
+            }
+        }
+        // `addrs` contains the real ports when `PORT` is `0`.
+        let bound_addresses = server.addrs();
+        lazy_message_writer.server_is_running(&bound_addresses, &CONFIG)?;
+
+        if CONFIG.redirect.enabled {
+            for ip in &CONFIG.bind_address {
+                server = server.bind(SocketAddr::new(*ip, CONFIG.redirect.port))?;
+            }
+            let redirect_addresses = server
+                .addrs()
+                .into_iter()
+                .filter(|address| not(bound_addresses.contains(address)))
+                .collect::<Vec<_>>();
+            lazy_message_writer.redirect_from(&redirect_addresses)?;
+        }
+    }
 
     // ------ Run ------
 
@@ -287,11 +350,66 @@ where
     if not(CONFIG.frontend_dist) {
         lazy_message_writer.write_all()?;
     }
-    server.await?;
+    jobs::start_registered();
+    let server_result = server.await;
+    jobs::cancel_all();
+    server_result?;
 
     Ok(println!("Stop Moon"))
 }
 
+// A socket file left by a previous run would make the binding fail.
+#[cfg(unix)]
+fn remove_stale_unix_socket(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+fn api_scope<UPH, UPHO, UMsg>() -> Scope
+where
+    UPH: UpHandler<UPHO, UMsg>,
+    UPHO: UpHandlerOutput,
+    UMsg: 'static + DeserializeOwned,
+{
+    web::scope("_api")
+        .route("public/{file:.*}", web::get().to(public_responder))
+        .route(
+            "public_{build_id}/{file:.*}",
+            web::get().to(fingerprinted_public_responder),
+        )
+        .route(
+            "up_msg_handler",
+            web::post().to(up_msg_handler_responder::<UPH, UPHO, UMsg>),
+        )
+        .route("cancel_up_msg", web::post().to(cancel_up_msg_responder))
+        .route("reload", web::post().to(reload_responder))
+        .route("pkg/{file:.*}", web::get().to(pkg_responder))
+        .route(
+            "message_sse/{session_id}",
+            web::get().to(message_sse_responder),
+        )
+        .route("reload_sse", web::get().to(reload_sse_responder))
+        .route(
+            "download/{token}",
+            web::get().to(download::download_responder),
+        )
+        .service(admin::scope())
+        .route("ping", web::to(|| async { "pong" }))
+        .route(
+            "{path:.*}",
+            web::to(|| async { HttpResponse::NotFound().reason("API Not Found").finish() }),
+        )
+}
+
+/// The frontend build id is used as the version because it changes whenever
+/// the frontend (and usually `UpMsg`/`DownMsg` in the shared crate) has been changed.
+fn version_header() -> DefaultHeaders {
+    DefaultHeaders::new().add((VERSION_HEADER, Frontend::version().to_string()))
+}
+
 async fn backend_build_id() -> u128 {
     fs::read_to_string("backend/private/build_id")
         .await
@@ -300,30 +418,6 @@ async fn backend_build_id() -> u128 {
         .unwrap_or_default()
 }
 
-fn rustls_server_config() -> io::Result<RustlsServerConfig> {
-    let key_file = &mut BufReader::new(File::open("backend/private/private.pem")?);
-    let key = pkcs8_private_keys(key_file)
-        .expect("private key parsing failed")
-        .into_iter()
-        .map(PrivateKey)
-        .next()
-        .expect("private key file has to contain at least one key");
-
-    let cert_file = &mut BufReader::new(File::open("backend/private/public.pem")?);
-    let certificates = certs(cert_file)
-        .expect("certificate parsing failed")
-        .into_iter()
-        .map(Certificate)
-        .collect();
-
-    let config = RustlsServerConfig::builder()
-        .with_safe_defaults()
-        .with_no_client_auth()
-        .with_single_cert(certificates, key)
-        .expect("private key is invalid");
-    Ok(config)
-}
-
 // ------ ------
 //  Responders
 // ------ ------
@@ -342,16 +436,143 @@ where
 {
     let headers = req.headers();
 
+    let session_id = parse_session_id(headers)?;
+    let cor_id = parse_cor_id(headers)?;
+    let auth_token = parse_auth_token(headers)?;
+    let session_actor = sessions::by_session_id().get(session_id);
+    if let Some(session_actor) = session_actor {
+        session_actor.touch();
+        if !session_actor.register_cor_id(cor_id) {
+            log::debug!("Duplicate UpMsg ignored (session_id: {session_id}, cor_id: {cor_id})");
+            return Ok(HttpResponse::Ok().finish());
+        }
+    }
+
+    let up_msg = match (parse_up_msg(payload).await, session_actor) {
+        (Ok(up_msg), _) => up_msg,
+        // The failure is reported to the waiting `exchange_msgs` call in zoon.
+        (Err(error), Some(session_actor)) => {
+            log::warn!("Invalid UpMsg: {error} (session_id: {session_id}, cor_id: {cor_id})");
+            let mut errors = ValidationErrors::new();
+            errors.push("", format!("invalid UpMsg: {error}"));
+            session_actor
+                .send_server_error(ServerError::Validation(errors), cor_id)
+                .await;
+            return Ok(HttpResponse::Ok().finish());
+        }
+        (Err(error), None) => Err(error)?,
+    };
     let up_msg_request = UpMsgRequest {
-        up_msg: parse_up_msg(payload).await?,
-        session_id: parse_session_id(headers)?,
-        cor_id: parse_cor_id(headers)?,
-        auth_token: parse_auth_token(headers)?,
+        up_msg,
+        session_id,
+        cor_id,
+        auth_token,
     };
-    up_msg_handler.get_ref()(up_msg_request).await;
+
+    if let Err(error) = handle_up_msg(up_msg_handler.get_ref(), up_msg_request).await {
+        log::error!("UpMsg handler {error} (session_id: {session_id}, cor_id: {cor_id})");
+        if let Some(session_actor) = sessions::by_session_id().get(session_id) {
+            session_actor.send_server_error(error.into(), cor_id).await;
+        }
+    }
     Ok(HttpResponse::Ok().finish())
 }
 
+// ------ validated ------
+
+/// Wraps `up_msg_handler` to validate `UpMsg`s before they are handled.
+/// An invalid `UpMsg` is rejected with `ServerError::Validation`.
+///
+/// ```ignore
+/// moon::start(frontend, validated(up_msg_handler), |_| {}).await
+/// ```
+pub fn validated<UPH, UPHO, UMsg>(
+    up_msg_handler: UPH,
+) -> impl Fn(UpMsgRequest<UMsg>) -> LocalBoxFuture<'static, ()> + Send + Sync + 'static
+where
+    UPH: UpHandler<UPHO, UMsg>,
+    UPHO: UpHandlerOutput,
+    UMsg: Validate + 'static,
+{
+    move |req| match req.up_msg.validate() {
+        Ok(()) => up_msg_handler(req).boxed_local(),
+        Err(errors) => async move {
+            let UpMsgRequest {
+                session_id, cor_id, ..
+            } = req;
+            log::warn!("Invalid UpMsg: {errors} (session_id: {session_id}, cor_id: {cor_id})");
+            if let Some(session_actor) = sessions::by_session_id().get(session_id) {
+                session_actor
+                    .send_server_error(ServerError::Validation(errors), cor_id)
+                    .await;
+            }
+        }
+        .boxed_local(),
+    }
+}
+
+async fn handle_up_msg<UPH, UPHO, UMsg>(
+    up_msg_handler: &UPH,
+    up_msg_request: UpMsgRequest<UMsg>,
+) -> Result<(), UpMsgHandlerError>
+where
+    UPH: UpHandler<UPHO, UMsg>,
+    UPHO: UpHandlerOutput,
+{
+    // The handler may panic also before it returns the future.
+    let handler = AssertUnwindSafe(async move { up_msg_handler(up_msg_request).await });
+    let handler = handler.catch_unwind();
+
+    let result = match CONFIG.up_msg_handler_timeout {
+        0 => handler.await,
+        timeout => {
+            let timeout = std::time::Duration::from_secs(timeout);
+            // The handler future is dropped (i.e. cancelled) on timeout.
+            rt::time::timeout(timeout, handler)
+                .await
+                .map_err(|_| UpMsgHandlerError::TimedOut(timeout))?
+        }
+    };
+    result.map_err(|panic| UpMsgHandlerError::Panicked(panic_message(panic.as_ref())))
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    if let Some(message) = panic.downcast_ref::<String>() {
+        return message.clone();
+    }
+    String::from("unknown panic payload")
+}
+
+// ------ UpMsgHandlerError ------
+
+#[derive(Debug)]
+enum UpMsgHandlerError {
+    Panicked(String),
+    // `std::time::Duration` explicitly because `Duration` is re-exported from `chrono`.
+    TimedOut(std::time::Duration),
+}
+
+impl From<UpMsgHandlerError> for ServerError {
+    fn from(error: UpMsgHandlerError) -> Self {
+        match error {
+            UpMsgHandlerError::Panicked(_) => ServerError::Internal(error.to_string()),
+            UpMsgHandlerError::TimedOut(_) => ServerError::Timeout(error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for UpMsgHandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panicked(message) => write!(f, "panicked: '{message}'"),
+            Self::TimedOut(timeout) => write!(f, "timed out after {timeout:?}"),
+        }
+    }
+}
+
 #[cfg(feature = "serde")]
 async fn parse_up_msg<UMsg: DeserializeOwned>(mut payload: web::Payload) -> Result<UMsg, Error> {
     let mut body = web::BytesMut::new();
@@ -383,7 +604,7 @@ async fn parse_up_msg<UMsg: Deserialize>(mut payload: web::Payload) -> Result<UM
         .map_err(error::ErrorBadRequest)
 }
 
-fn parse_session_id(headers: &HeaderMap) -> Result<SessionId, Error> {
+pub(crate) fn parse_session_id(headers: &HeaderMap) -> Result<SessionId, Error> {
     headers
         .get("X-Session-ID")
         .ok_or_else(|| error::ErrorBadRequest("header 'X-Session-ID' is missing"))?
@@ -393,7 +614,7 @@ fn parse_session_id(headers: &HeaderMap) -> Result<SessionId, Error> {
         .map_err(error::ErrorBadRequest)
 }
 
-fn parse_cor_id(headers: &HeaderMap) -> Result<CorId, Error> {
+pub(crate) fn parse_cor_id(headers: &HeaderMap) -> Result<CorId, Error> {
     headers
         .get("X-Correlation-ID")
         .ok_or_else(|| error::ErrorBadRequest("header 'X-Correlation-ID' is missing"))?
@@ -403,7 +624,7 @@ fn parse_cor_id(headers: &HeaderMap) -> Result<CorId, Error> {
         .map_err(error::ErrorBadRequest)
 }
 
-fn parse_auth_token(headers: &HeaderMap) -> Result<Option<AuthToken>, Error> {
+pub(crate) fn parse_auth_token(headers: &HeaderMap) -> Result<Option<AuthToken>, Error> {
     if let Some(auth_token) = headers.get("X-Auth-Token") {
         let auth_token = auth_token
             .to_str()
@@ -414,6 +635,18 @@ fn parse_auth_token(headers: &HeaderMap) -> Result<Option<AuthToken>, Error> {
     Ok(None)
 }
 
+// ------ cancel_up_msg_responder ------
+
+async fn cancel_up_msg_responder(req: HttpRequest) -> Result<HttpResponse, Error> {
+    let headers = req.headers();
+    let session_id = parse_session_id(headers)?;
+    let cor_id = parse_cor_id(headers)?;
+    if let Some(session_actor) = sessions::by_session_id().get(session_id) {
+        session_actor.cancel(cor_id);
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
 // ------ reload_responder ------
 
 async fn reload_responder(sse: web::Data<ReloadSSE>) -> impl Responder {
@@ -463,12 +696,7 @@ fn named_file_and_encoding(
     if !shared_data.compressed_pkg {
         return Ok((NamedFile::open(file)?, None));
     }
-    let accept_encodings = req
-        .headers()
-        .get(header::ACCEPT_ENCODING)
-        .and_then(|accept_encoding| accept_encoding.to_str().ok())
-        .map(|accept_encoding| accept_encoding.split(", ").collect::<BTreeSet<_>>())
-        .unwrap_or_default();
+    let accept_encodings = accept_encodings(req);
 
     if accept_encodings.contains(ContentEncoding::Brotli.as_str()) {
         file.push_str(".br");
@@ -489,6 +717,124 @@ fn named_file_and_encoding(
     Ok((NamedFile::open(file)?, None))
 }
 
+fn accept_encodings(req: &HttpRequest) -> BTreeSet<&str> {
+    req.headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|accept_encoding| accept_encoding.to_str().ok())
+        .map(|accept_encoding| accept_encoding.split(", ").collect::<BTreeSet<_>>())
+        .unwrap_or_default()
+}
+
+// ------ public_responder ------
+
+async fn public_responder(
+    req: HttpRequest,
+    file: web::Path<String>,
+    shared_data: web::Data<SharedData>,
+) -> impl Responder {
+    public_file_response(&req, &file, &shared_data, false)
+}
+
+async fn fingerprinted_public_responder(
+    req: HttpRequest,
+    path: web::Path<(u128, String)>,
+    shared_data: web::Data<SharedData>,
+) -> impl Responder {
+    let (build_id, file) = path.into_inner();
+    // Files requested with an outdated fingerprint are served as well,
+    // but they mustn't be cached forever under the old URL.
+    let immutable = build_id == shared_data.frontend_build_id;
+    public_file_response(&req, &file, &shared_data, immutable)
+}
+
+fn public_file_response(
+    req: &HttpRequest,
+    file: &str,
+    shared_data: &SharedData,
+    immutable: bool,
+) -> Result<HttpResponse, Error> {
+    let file_path = Path::new(file);
+    let is_safe_path = file_path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !is_safe_path {
+        return Ok(HttpResponse::NotFound().reason("File Not Found").finish());
+    }
+
+    let mime = mime_guess::from_path(file_path).first_or_octet_stream();
+    let (named_file, encoding) = match public_named_file_and_encoding(req, file, shared_data) {
+        Ok(named_file_and_encoding) => named_file_and_encoding,
+        Err(_) => return Ok(HttpResponse::NotFound().reason("File Not Found").finish()),
+    };
+
+    let named_file = named_file
+        .set_content_type(mime)
+        .prefer_utf8(true)
+        .use_etag(!immutable)
+        .use_last_modified(!immutable)
+        .disable_content_disposition()
+        .customize();
+
+    let mut responder = if immutable {
+        named_file.insert_header(CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(31536000),
+            CacheDirective::Extension("immutable".to_owned(), None),
+        ]))
+    } else {
+        named_file.insert_header(CacheControl(vec![CacheDirective::NoCache]))
+    };
+
+    if shared_data.compressed_public {
+        responder = responder.insert_header((header::VARY, header::ACCEPT_ENCODING.as_str()));
+    }
+    if let Some(encoding) = encoding {
+        responder = responder.insert_header(encoding);
+    }
+    Ok(responder.respond_to(req).map_into_boxed_body())
+}
+
+fn public_named_file_and_encoding(
+    req: &HttpRequest,
+    file: &str,
+    shared_data: &SharedData,
+) -> io::Result<(NamedFile, Option<ContentEncoding>)> {
+    let original_file = format!("{}/{}", shared_data.public_path, file);
+    if shared_data.compressed_public {
+        let accept_encodings = accept_encodings(req);
+        for (encoding, extension) in [
+            (ContentEncoding::Brotli, "br"),
+            (ContentEncoding::Gzip, "gz"),
+        ] {
+            if accept_encodings.contains(encoding.as_str()) {
+                let compressed_file = format!(
+                    "{}/{}.{}",
+                    shared_data.compressed_public_path, file, extension
+                );
+                // Not every public file has to be compressed (e.g. files added after the build)
+                // and a compressed file may be outdated (e.g. the original has been replaced
+                // on the server), so we fall back to the original file.
+                if let Ok(named_file) = NamedFile::open(compressed_file) {
+                    if !is_compressed_file_outdated(&named_file, &original_file) {
+                        return Ok((named_file, Some(encoding)));
+                    }
+                }
+            }
+        }
+    }
+    Ok((NamedFile::open(original_file)?, None))
+}
+
+fn is_compressed_file_outdated(compressed_file: &NamedFile, original_file: &str) -> bool {
+    let compressed_modified = compressed_file.metadata().modified();
+    let original_modified =
+        std::fs::metadata(original_file).and_then(|metadata| metadata.modified());
+    match (compressed_modified, original_modified) {
+        (Ok(compressed_modified), Ok(original_modified)) => compressed_modified < original_modified,
+        _ => false,
+    }
+}
+
 // ------ reload_sse_responder ------
 
 async fn reload_sse_responder(
@@ -520,8 +866,24 @@ async fn message_sse_responder(
     sse: web::Data<MessageSSE>,
 ) -> Result<HttpResponse, Error> {
     let session_id = session_id.parse().map_err(error::ErrorBadRequest)?;
-    let (_, event_stream) = sse.new_connection(Some(session_id));
-    SessionActor::create(session_id, MessageSSE::clone(&sse));
+    let (connection, event_stream) = sse.new_connection(Some(session_id));
+
+    // The version is sent again on every reconnection, e.g. after a redeploy.
+    let version = Frontend::version().to_string();
+    if connection.send("version", &version).is_err() {
+        return Ok(HttpResponse::InternalServerError()
+            .reason("sending version failed")
+            .finish());
+    }
+    // The session id is persisted in the browser, so a reloaded page
+    // or a reconnected `EventSource` resumes the existing session.
+    let resumed = match sessions::by_session_id().get(session_id) {
+        Some(session_actor) => session_actor.resume(),
+        None => false,
+    };
+    if !resumed {
+        SessionActor::create(session_id, MessageSSE::clone(&sse));
+    }
 
     Ok(HttpResponse::Ok()
         .insert_header(ContentType(mime::TEXT_EVENT_STREAM))
@@ -562,7 +924,10 @@ mod tests {
             backend_build_id: u128::default(),
             cache_busting: bool::default(),
             compressed_pkg: false,
+            compressed_public: bool::default(),
             pkg_path: FIXTURES_DIR,
+            public_path: FIXTURES_DIR,
+            compressed_public_path: FIXTURES_DIR,
         };
         let app = test::init_service(
             App::new()
@@ -603,7 +968,10 @@ mod tests {
             backend_build_id: u128::default(),
             cache_busting: bool::default(),
             compressed_pkg: true,
+            compressed_public: bool::default(),
             pkg_path: FIXTURES_DIR,
+            public_path: FIXTURES_DIR,
+            compressed_public_path: FIXTURES_DIR,
         };
         let app = test::init_service(
             App::new()
@@ -650,7 +1018,10 @@ mod tests {
             backend_build_id: u128::default(),
             cache_busting: bool::default(),
             compressed_pkg: true,
+            compressed_public: bool::default(),
             pkg_path: FIXTURES_DIR,
+            public_path: FIXTURES_DIR,
+            compressed_public_path: FIXTURES_DIR,
         };
         let app = test::init_service(
             App::new()
@@ -686,4 +1057,639 @@ mod tests {
         );
         assert_eq!(body::to_bytes(resp.into_body()).await.unwrap(), css_content,);
     }
+
+    // Copies `index.css` and `index.css.br` to a new directory.
+    // Git doesn't preserve modification times, so we have to set them explicitly.
+    fn public_dir_with_compressed_css(compressed_outdated: bool) -> &'static str {
+        let dir = std::env::temp_dir().join(format!("moon_public_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let now = std::time::SystemTime::now();
+        let hour_ago = now - std::time::Duration::from_secs(60 * 60);
+        let (original_modified, compressed_modified) = if compressed_outdated {
+            (now, hour_ago)
+        } else {
+            (hour_ago, now)
+        };
+        for (file, modified) in [
+            ("index.css", original_modified),
+            ("index.css.br", compressed_modified),
+        ] {
+            let path = dir.join(file);
+            std::fs::copy(format!("{FIXTURES_DIR}/{file}"), &path).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        Box::leak(dir.to_string_lossy().into_owned().into_boxed_str())
+    }
+
+    #[actix_rt::test]
+    async fn test_public_brotli_compressed() {
+        // ------ ARRANGE ------
+        let css_content = web::Bytes::from_static(include_bytes!("../tests/fixtures/index.css.br"));
+        let public_dir = public_dir_with_compressed_css(false);
+
+        let shared_data = SharedData {
+            frontend_build_id: u128::default(),
+            backend_build_id: u128::default(),
+            cache_busting: bool::default(),
+            compressed_pkg: bool::default(),
+            compressed_public: true,
+            pkg_path: FIXTURES_DIR,
+            public_path: public_dir,
+            compressed_public_path: public_dir,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(shared_data))
+                .route("_api/public/{file:.*}", web::get().to(public_responder)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .insert_header((header::ACCEPT_ENCODING, ContentEncoding::Br.as_str()))
+            .uri("/_api/public/index.css")
+            .to_request();
+
+        // ------ ACT ------
+        let resp = test::call_service(&app, req).await;
+
+        // ------ ASSERT ------
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(header::CONTENT_TYPE)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            mime::TEXT_CSS_UTF_8.to_string()
+        );
+        assert_eq!(
+            resp.headers()
+                .get(header::CONTENT_ENCODING)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            ContentEncoding::Br.as_str()
+        );
+        assert_eq!(body::to_bytes(resp.into_body()).await.unwrap(), css_content,);
+    }
+
+    #[actix_rt::test]
+    async fn test_public_outdated_compressed() {
+        // ------ ARRANGE ------
+        let css_content = web::Bytes::from_static(include_bytes!("../tests/fixtures/index.css"));
+        let public_dir = public_dir_with_compressed_css(true);
+
+        let shared_data = SharedData {
+            frontend_build_id: u128::default(),
+            backend_build_id: u128::default(),
+            cache_busting: bool::default(),
+            compressed_pkg: bool::default(),
+            compressed_public: true,
+            pkg_path: FIXTURES_DIR,
+            public_path: public_dir,
+            compressed_public_path: public_dir,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(shared_data))
+                .route("_api/public/{file:.*}", web::get().to(public_responder)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .insert_header((header::ACCEPT_ENCODING, ContentEncoding::Br.as_str()))
+            .uri("/_api/public/index.css")
+            .to_request();
+
+        // ------ ACT ------
+        let resp = test::call_service(&app, req).await;
+
+        // ------ ASSERT ------
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(header::CONTENT_TYPE)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            mime::TEXT_CSS_UTF_8.to_string()
+        );
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(body::to_bytes(resp.into_body()).await.unwrap(), css_content,);
+    }
+
+    #[actix_rt::test]
+    async fn test_public_fingerprinted() {
+        // ------ ARRANGE ------
+        let css_content = include_str!("../tests/fixtures/index.css");
+
+        let shared_data = SharedData {
+            frontend_build_id: 123,
+            backend_build_id: u128::default(),
+            cache_busting: bool::default(),
+            compressed_pkg: bool::default(),
+            compressed_public: bool::default(),
+            pkg_path: FIXTURES_DIR,
+            public_path: FIXTURES_DIR,
+            compressed_public_path: FIXTURES_DIR,
+        };
+        let app = test::init_service(App::new().app_data(Data::new(shared_data)).route(
+            "_api/public_{build_id}/{file:.*}",
+            web::get().to(fingerprinted_public_responder),
+        ))
+        .await;
+        let req = test::TestRequest::get()
+            .insert_header((header::ACCEPT_ENCODING, ContentEncoding::Br.as_str()))
+            .uri("/_api/public_123/index.css")
+            .to_request();
+
+        // ------ ACT ------
+        let resp = test::call_service(&app, req).await;
+
+        // ------ ASSERT ------
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(header::CACHE_CONTROL)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "public, max-age=31536000, immutable"
+        );
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(
+            body::to_bytes(resp.into_body()).await.unwrap(),
+            css_content.as_bytes()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_public_path_traversal() {
+        // ------ ARRANGE ------
+        let shared_data = SharedData {
+            frontend_build_id: u128::default(),
+            backend_build_id: u128::default(),
+            cache_busting: bool::default(),
+            compressed_pkg: bool::default(),
+            compressed_public: bool::default(),
+            pkg_path: FIXTURES_DIR,
+            public_path: FIXTURES_DIR,
+            compressed_public_path: FIXTURES_DIR,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(shared_data))
+                .route("_api/public/{file:.*}", web::get().to(public_responder)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/_api/public/../fixtures/index.css")
+            .to_request();
+
+        // ------ ACT ------
+        let resp = test::call_service(&app, req).await;
+
+        // ------ ASSERT ------
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_one_time_download() {
+        // ------ ARRANGE ------
+        let css_content = include_str!("../tests/fixtures/index.css");
+
+        let url = Download::new(concatcp!(FIXTURES_DIR, "/index.css"))
+            .file_name("styles.css")
+            .url();
+        let app = test::init_service(App::new().route(
+            "_api/download/{token}",
+            web::get().to(download::download_responder),
+        ))
+        .await;
+
+        // ------ ACT ------
+        let resp = test::call_service(&app, test::TestRequest::get().uri(&url).to_request()).await;
+        let second_resp =
+            test::call_service(&app, test::TestRequest::get().uri(&url).to_request()).await;
+
+        // ------ ASSERT ------
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(header::CONTENT_DISPOSITION)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            r#"attachment; filename="styles.css""#
+        );
+        assert_eq!(
+            body::to_bytes(resp.into_body()).await.unwrap(),
+            css_content.as_bytes()
+        );
+        assert_eq!(second_resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_app_broadcast() {
+        // ------ ARRANGE ------
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            |req: UpMsgRequest<String>| async move {
+                sessions::broadcast_down_msg(&req.up_msg.to_uppercase(), req.cor_id).await
+            },
+            |_| {},
+        )
+        .await;
+        let mut alice = app.session().await;
+        let mut bob = app.session().await;
+
+        // ------ ACT ------
+        let cor_id = alice.send_up_msg(&"hello").await;
+
+        // ------ ASSERT ------
+        assert_eq!(alice.down_msg::<String>(cor_id).await, "HELLO");
+        assert_eq!(
+            bob.next_down_msg::<String>().await,
+            ("HELLO".to_owned(), cor_id)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_up_msg_handler_panic() {
+        // ------ ARRANGE ------
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            |req: UpMsgRequest<String>| async move {
+                if req.up_msg == "panic" {
+                    panic!("invalid UpMsg");
+                }
+            },
+            |_| {},
+        )
+        .await;
+        let mut session = app.session().await;
+
+        // ------ ACT ------
+        let cor_id = session.send_up_msg(&"panic").await;
+
+        // ------ ASSERT ------
+        assert_eq!(
+            session.server_error(cor_id).await,
+            ServerError::Internal("panicked: 'invalid UpMsg'".to_owned())
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_invalid_up_msg() {
+        // ------ ARRANGE ------
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            |_: UpMsgRequest<String>| async {},
+            |_| {},
+        )
+        .await;
+        let mut session = app.session().await;
+
+        // ------ ACT ------
+        let cor_id = session.send_up_msg(&42).await;
+
+        // ------ ASSERT ------
+        assert!(matches!(
+            session.server_error(cor_id).await,
+            ServerError::Validation(_)
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_validated_up_msg() {
+        // ------ ARRANGE ------
+        #[derive(Serialize, Deserialize, Validate)]
+        #[serde(crate = "serde")]
+        struct Login {
+            #[validate(length(min = 1))]
+            name: String,
+        }
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            validated(|req: UpMsgRequest<Login>| async move {
+                sessions::broadcast_down_msg(&req.up_msg.name, req.cor_id).await
+            }),
+            |_| {},
+        )
+        .await;
+        let mut session = app.session().await;
+
+        // ------ ACT ------
+        let invalid_cor_id = session
+            .send_up_msg(&Login {
+                name: String::new(),
+            })
+            .await;
+        let valid_cor_id = session
+            .send_up_msg(&Login {
+                name: "Martin".to_owned(),
+            })
+            .await;
+
+        // ------ ASSERT ------
+        match session.server_error(invalid_cor_id).await {
+            ServerError::Validation(errors) => assert_eq!(errors.field_messages("name").count(), 1),
+            error => panic!("validation error expected, got: {error}"),
+        }
+        assert_eq!(session.down_msg::<String>(valid_cor_id).await, "Martin");
+    }
+
+    #[actix_rt::test]
+    async fn test_duplicate_up_msg() {
+        // ------ ARRANGE ------
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            |req: UpMsgRequest<String>| async move {
+                sessions::broadcast_down_msg(&req.up_msg, req.cor_id).await
+            },
+            |_| {},
+        )
+        .await;
+        let mut session = app.session().await;
+        let cor_id = CorId::new();
+
+        // ------ ACT ------
+        session.send_up_msg_with_cor_id(&"first", cor_id).await;
+        session.send_up_msg_with_cor_id(&"retry", cor_id).await;
+        let next_cor_id = session.send_up_msg(&"second").await;
+
+        // ------ ASSERT ------
+        assert_eq!(
+            session.next_down_msg::<String>().await,
+            ("first".to_owned(), cor_id)
+        );
+        assert_eq!(
+            session.next_down_msg::<String>().await,
+            ("second".to_owned(), next_cor_id)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_cancelled_up_msg() {
+        // ------ ARRANGE ------
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            |req: UpMsgRequest<String>| async move {
+                sessions::broadcast_down_msg(&req.is_cancelled(), req.cor_id).await
+            },
+            |_| {},
+        )
+        .await;
+        let mut session = app.session().await;
+        let cancelled_cor_id = CorId::new();
+
+        // ------ ACT ------
+        session.cancel_up_msg(cancelled_cor_id).await;
+        session
+            .send_up_msg_with_cor_id(&"cancelled", cancelled_cor_id)
+            .await;
+        let cor_id = session.send_up_msg(&"not cancelled").await;
+
+        // ------ ASSERT ------
+        assert!(session.down_msg::<bool>(cancelled_cor_id).await);
+        assert!(!session.down_msg::<bool>(cor_id).await);
+    }
+
+    #[actix_rt::test]
+    async fn test_cancelled_on_session_removal() {
+        // ------ ARRANGE ------
+        let session_actor = SessionActor::create(SessionId::new(), MessageSSE(SSE::start()));
+        let cor_id = CorId::new();
+        let waiter = actix_rt::spawn(async move { session_actor.cancelled(cor_id).await });
+        actix_rt::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        // ------ ACT ------
+        session_actor.remove();
+
+        // ------ ASSERT ------
+        actix_rt::time::timeout(std::time::Duration::from_secs(5), waiter)
+            .await
+            .expect("cancelled() hasn't resolved after the session removal")
+            .unwrap();
+        assert!(session_actor.is_cancelled(cor_id));
+    }
+
+    #[actix_rt::test]
+    async fn test_not_cancelled_without_session() {
+        // ------ ARRANGE ------
+        let req = UpMsgRequest {
+            up_msg: (),
+            session_id: SessionId::new(),
+            cor_id: CorId::new(),
+            auth_token: None,
+        };
+
+        // ------ ACT ------
+        let cancelled =
+            actix_rt::time::timeout(std::time::Duration::from_millis(50), req.cancelled()).await;
+
+        // ------ ASSERT ------
+        assert!(!req.is_cancelled());
+        assert!(cancelled.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_version_header() {
+        // ------ ARRANGE ------
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            |_: UpMsgRequest<String>| async {},
+            |_| {},
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/_api/ping").to_request();
+
+        // ------ ACT ------
+        let resp = app.call(req).await;
+
+        // ------ ASSERT ------
+        assert_eq!(
+            resp.headers()
+                .get(VERSION_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            u128::default().to_string()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_wait_for_session() {
+        // ------ ARRANGE ------
+        let session_id = SessionId::new();
+        let timeout = std::time::Duration::from_secs(5);
+        let waiter = actix_rt::spawn(async move {
+            sessions::by_session_id()
+                .wait_for_with_timeout(session_id, timeout)
+                .await
+                .is_some()
+        });
+
+        // ------ ACT ------
+        actix_rt::time::sleep(std::time::Duration::from_millis(50)).await;
+        SessionActor::create(session_id, MessageSSE(SSE::start()));
+
+        // ------ ASSERT ------
+        assert!(waiter.await.unwrap());
+        assert!(sessions::by_session_id()
+            .wait_for_with_timeout(SessionId::new(), std::time::Duration::from_millis(50))
+            .await
+            .is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_session_resumption() {
+        // ------ ARRANGE ------
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            |_: UpMsgRequest<String>| async {},
+            |_| {},
+        )
+        .await;
+        let session_id = SessionId::new();
+        let connect = || {
+            test::TestRequest::get()
+                .uri(&format!("/_api/message_sse/{session_id}"))
+                .to_request()
+        };
+        let session_infos = || {
+            sessions::infos()
+                .into_iter()
+                .filter(|info| info.session_id == session_id)
+                .collect::<Vec<_>>()
+        };
+        let first_resp = app.call(connect()).await;
+        let connected_at = session_infos()[0].connected_at;
+
+        // ------ ACT ------
+        drop(first_resp);
+        let second_resp = app.call(connect()).await;
+
+        // ------ ASSERT ------
+        assert!(second_resp.status().is_success());
+        let session_infos = session_infos();
+        assert_eq!(session_infos.len(), 1);
+        assert_eq!(session_infos[0].connected_at, connected_at);
+    }
+
+    #[actix_rt::test]
+    async fn test_session_reconnect_after_removal() {
+        // ------ ARRANGE ------
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            |req: UpMsgRequest<String>| async move {
+                sessions::broadcast_down_msg(&req.up_msg, req.cor_id).await
+            },
+            |_| {},
+        )
+        .await;
+        let session = app.session().await;
+        let session_id = session.session_id();
+
+        // ------ ACT ------
+        sessions::by_session_id().get(session_id).unwrap().remove();
+        drop(session);
+        let mut session = app.session_with_id(session_id).await;
+        let cor_id = session.send_up_msg(&"after removal").await;
+
+        // ------ ASSERT ------
+        assert_eq!(session.down_msg::<String>(cor_id).await, "after removal");
+        assert_eq!(
+            sessions::infos()
+                .into_iter()
+                .filter(|info| info.session_id == session_id)
+                .count(),
+            1
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_multipart_upload() {
+        // ------ ARRANGE ------
+        let uploads = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            |_: UpMsgRequest<String>| async {},
+            {
+                let uploads = Arc::clone(&uploads);
+                move |cfg: &mut web::ServiceConfig| {
+                    let uploads = Arc::clone(&uploads);
+                    let upload_handler = move |req: upload::UploadRequest| {
+                        let content = std::fs::read_to_string(req.file.path()).unwrap();
+                        uploads.lock().push((
+                            req.file.file_name().map(ToOwned::to_owned),
+                            req.file.content_type().map(ToString::to_string),
+                            content,
+                        ));
+                        async {}
+                    };
+                    cfg.service(upload::route(upload_handler, upload::UploadLimits::new()));
+                }
+            },
+        )
+        .await;
+        let body = concat!(
+            "--boundary\r\n",
+            "Content-Disposition: form-data; name=\"title\"\r\n\r\n",
+            "My notes\r\n",
+            "--boundary\r\n",
+            "Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\n",
+            "Content-Type: text/plain\r\n\r\n",
+            "Hello, Moon!\r\n",
+            "--boundary--\r\n",
+        );
+        let req = test::TestRequest::post()
+            .uri("/_api/upload")
+            .insert_header(("X-Session-ID", SessionId::new().to_string()))
+            .insert_header(("X-Correlation-ID", CorId::new().to_string()))
+            .insert_header((
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=boundary",
+            ))
+            .set_payload(body)
+            .to_request();
+
+        // ------ ACT ------
+        let resp = app.call(req).await;
+
+        // ------ ASSERT ------
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            *uploads.lock(),
+            [(
+                Some("notes.txt".to_owned()),
+                Some("text/plain".to_owned()),
+                "Hello, Moon!".to_owned()
+            )]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_admin_api_disabled_without_token() {
+        // ------ ARRANGE ------
+        let app = testing::TestApp::new(
+            || async { Frontend::new() },
+            |_: UpMsgRequest<String>| async {},
+            |_| {},
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/_api/admin/status")
+            .insert_header((header::AUTHORIZATION, "Bearer "))
+            .to_request();
+
+        // ------ ACT ------
+        let resp = app.call(req).await;
+
+        // ------ ASSERT ------
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
 }
@@ -1,26 +1,52 @@
 use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
 use actix_web::http::header::LOCATION;
-use actix_web::http::uri::{Authority, InvalidUriParts, Scheme, Uri};
+use actix_web::http::uri::{Authority, InvalidUriParts, PathAndQuery, Scheme, Uri};
 use actix_web::{body::EitherBody, Error, HttpResponse};
 use bool_ext::BoolExt;
 use futures::future::{ok, Either, FutureExt, LocalBoxFuture, Ready};
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
+// ------ TrailingSlash ------
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailingSlash {
+    Keep,
+    Add,
+    Remove,
+}
+
+impl Default for TrailingSlash {
+    fn default() -> Self {
+        Self::Keep
+    }
+}
+
 // ------ Redirect ------
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct Redirect {
+    port_redirect: bool,
     http_to_https: bool,
     from_port: u16,
     to_port: u16,
+    canonical_host: Option<String>,
+    trailing_slash: TrailingSlash,
+    permanent: BTreeMap<String, String>,
 }
 
 impl Default for Redirect {
     fn default() -> Self {
         Self {
+            port_redirect: true,
             http_to_https: true,
             from_port: 80,
             to_port: 443,
+            canonical_host: None,
+            trailing_slash: TrailingSlash::default(),
+            permanent: BTreeMap::new(),
         }
     }
 }
@@ -30,6 +56,11 @@ impl Redirect {
         Self::default()
     }
 
+    pub fn port_redirect(mut self, enabled: bool) -> Self {
+        self.port_redirect = enabled;
+        self
+    }
+
     pub fn http_to_https(mut self, http_to_https: bool) -> Self {
         self.http_to_https = http_to_https;
         self
@@ -40,6 +71,25 @@ impl Redirect {
         self.to_port = to_port;
         self
     }
+
+    /// Redirects `www.example.com` to `example.com` or vice versa.
+    /// Requests to other hosts (e.g. `localhost`) are not redirected.
+    pub fn canonical_host(mut self, host: impl Into<Option<String>>) -> Self {
+        self.canonical_host = host.into();
+        self
+    }
+
+    /// Adds or removes trailing slashes in paths. `/` and `/_api/*` are ignored.
+    pub fn trailing_slash(mut self, trailing_slash: TrailingSlash) -> Self {
+        self.trailing_slash = trailing_slash;
+        self
+    }
+
+    /// `to` may be a path (`/new-page`) or an absolute URL (`https://example.com/new-page`).
+    pub fn permanent(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.permanent.insert(from.into(), to.into());
+        self
+    }
 }
 
 impl<S, B> Transform<S, ServiceRequest> for Redirect
@@ -57,7 +107,7 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ok(RedirectMiddleware {
             service,
-            redirect: *self,
+            redirect: self.clone(),
         })
     }
 }
@@ -82,6 +132,7 @@ impl<S> RedirectMiddleware<S> {
     }
 
     fn should_redirect(&self, uri: &Uri) -> Option<()> {
+        self.redirect.port_redirect.to_option()?;
         let from_port = self.redirect.from_port;
 
         match (uri.scheme()?, uri.authority()?.port_u16()) {
@@ -107,6 +158,47 @@ impl<S> RedirectMiddleware<S> {
         Uri::from_parts(uri_parts).ok()
     }
 
+    fn rules_redirect_uri(&self, uri: &Uri) -> Option<Uri> {
+        let host = self
+            .redirect
+            .canonical_host
+            .as_deref()
+            .and_then(|canonical_host| canonical_host_for(uri.host()?, canonical_host));
+
+        let path_and_query = match self.redirect.permanent.get(uri.path()) {
+            Some(to) if !to.starts_with('/') => {
+                return Uri::try_from(with_query(to, uri.query())).ok()
+            }
+            Some(to) => Some(with_query(to, uri.query())),
+            None => normalized_path(uri.path(), self.redirect.trailing_slash).map(|path| match uri
+                .query()
+            {
+                Some(query) => format!("{path}?{query}"),
+                None => path,
+            }),
+        };
+
+        if host.is_none() && path_and_query.is_none() {
+            return None;
+        }
+        self.uri_with(uri, host, path_and_query.as_deref())
+    }
+
+    fn uri_with(&self, uri: &Uri, host: Option<&str>, path_and_query: Option<&str>) -> Option<Uri> {
+        let mut uri_parts = uri.clone().into_parts();
+        if let Some(host) = host {
+            let authority = match uri.port_u16() {
+                Some(port) => format!("{host}:{port}"),
+                None => host.to_owned(),
+            };
+            uri_parts.authority = Authority::try_from(authority).ok();
+        }
+        if let Some(path_and_query) = path_and_query {
+            uri_parts.path_and_query = PathAndQuery::try_from(path_and_query).ok();
+        }
+        Uri::from_parts(uri_parts).ok()
+    }
+
     fn redirect<B>(
         &self,
         req: ServiceRequest,
@@ -142,6 +234,9 @@ where
                 let redirect_uri = self.redirect_uri(uri).unwrap();
                 return self.redirect(req, &redirect_uri).right_future();
             }
+            if let Some(redirect_uri) = self.rules_redirect_uri(&uri) {
+                return self.redirect(req, &redirect_uri).right_future();
+            }
         }
         self.service
             .call(req)
@@ -150,3 +245,164 @@ where
             .left_future()
     }
 }
+
+// ------ helpers ------
+
+fn canonical_host_for<'a>(host: &str, canonical_host: &'a str) -> Option<&'a str> {
+    if host.eq_ignore_ascii_case(canonical_host) {
+        return None;
+    }
+    let is_counterpart = match canonical_host.strip_prefix("www.") {
+        Some(apex) => host.eq_ignore_ascii_case(apex),
+        None => host
+            .strip_prefix("www.")
+            .map_or(false, |apex| apex.eq_ignore_ascii_case(canonical_host)),
+    };
+    is_counterpart.then(|| canonical_host)
+}
+
+// The request's query is appended to the query of the redirect target, if any.
+fn with_query(to: &str, query: Option<&str>) -> String {
+    match query {
+        Some(query) if to.contains('?') => format!("{to}&{query}"),
+        Some(query) => format!("{to}?{query}"),
+        None => to.to_owned(),
+    }
+}
+
+fn normalized_path(path: &str, trailing_slash: TrailingSlash) -> Option<String> {
+    if path == "/" || path.starts_with("/_api/") {
+        return None;
+    }
+    match trailing_slash {
+        TrailingSlash::Keep => None,
+        TrailingSlash::Add => {
+            let last_segment = path.rsplit('/').next().unwrap_or_default();
+            // Paths to files like `/robots.txt` are kept untouched.
+            (!path.ends_with('/') && !last_segment.contains('.')).then(|| format!("{path}/"))
+        }
+        TrailingSlash::Remove => {
+            let trimmed_path = path.trim_end_matches('/');
+            (path.ends_with('/') && !trimmed_path.is_empty()).then(|| trimmed_path.to_owned())
+        }
+    }
+}
+
+// ====== ====== TESTS ====== ======
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::{header::HOST, StatusCode};
+    use actix_web::{rt as actix_rt, test, web, App};
+
+    async fn location(redirect: Redirect, host: &str, uri: &str) -> Option<String> {
+        let app = test::init_service(
+            App::new()
+                .wrap(redirect)
+                .default_service(web::to(|| async { HttpResponse::Ok().finish() })),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .insert_header((HOST, host))
+            .uri(uri)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        if resp.status() == StatusCode::OK {
+            return None;
+        }
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        Some(resp.headers().get(LOCATION)?.to_str().unwrap().to_owned())
+    }
+
+    fn rules_redirect() -> Redirect {
+        Redirect::new().port_redirect(false)
+    }
+
+    #[test]
+    fn test_canonical_host_for() {
+        assert_eq!(
+            canonical_host_for("www.example.com", "example.com"),
+            Some("example.com")
+        );
+        assert_eq!(
+            canonical_host_for("example.com", "www.example.com"),
+            Some("www.example.com")
+        );
+        assert_eq!(canonical_host_for("example.com", "example.com"), None);
+        assert_eq!(canonical_host_for("localhost", "example.com"), None);
+        assert_eq!(canonical_host_for("api.example.com", "example.com"), None);
+    }
+
+    #[test]
+    fn test_normalized_path() {
+        assert_eq!(
+            normalized_path("/about", TrailingSlash::Add),
+            Some("/about/".to_owned())
+        );
+        assert_eq!(normalized_path("/about/", TrailingSlash::Add), None);
+        assert_eq!(normalized_path("/robots.txt", TrailingSlash::Add), None);
+        assert_eq!(
+            normalized_path("/about/", TrailingSlash::Remove),
+            Some("/about".to_owned())
+        );
+        assert_eq!(normalized_path("/about", TrailingSlash::Remove), None);
+        assert_eq!(normalized_path("/", TrailingSlash::Remove), None);
+        assert_eq!(normalized_path("/_api/pkg/", TrailingSlash::Remove), None);
+        assert_eq!(normalized_path("/about/", TrailingSlash::Keep), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_canonical_host_redirect() {
+        let redirect = rules_redirect().canonical_host("example.com".to_owned());
+        assert_eq!(
+            location(redirect.clone(), "www.example.com", "/about?page=2").await,
+            Some("http://example.com/about?page=2".to_owned())
+        );
+        assert_eq!(location(redirect, "example.com", "/about").await, None);
+    }
+
+    #[actix_rt::test]
+    async fn test_trailing_slash_redirect() {
+        let redirect = rules_redirect().trailing_slash(TrailingSlash::Remove);
+        assert_eq!(
+            location(redirect.clone(), "example.com:8080", "/about/?page=2").await,
+            Some("http://example.com:8080/about?page=2".to_owned())
+        );
+        assert_eq!(location(redirect, "example.com:8080", "/about").await, None);
+    }
+
+    #[actix_rt::test]
+    async fn test_permanent_redirect() {
+        let redirect = rules_redirect()
+            .canonical_host("example.com".to_owned())
+            .permanent("/old-page", "/new-page")
+            .permanent("/blog", "https://blog.example.com/");
+        assert_eq!(
+            location(redirect.clone(), "www.example.com", "/old-page").await,
+            Some("http://example.com/new-page".to_owned())
+        );
+        assert_eq!(
+            location(redirect.clone(), "example.com", "/blog").await,
+            Some("https://blog.example.com/".to_owned())
+        );
+        assert_eq!(
+            location(redirect.clone(), "example.com", "/old-page?ref=a,b").await,
+            Some("http://example.com/new-page?ref=a,b".to_owned())
+        );
+        assert_eq!(
+            location(redirect, "example.com", "/blog?page=2").await,
+            Some("https://blog.example.com/?page=2".to_owned())
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_port_redirect() {
+        let redirect = Redirect::new().http_to_https(true).port(8081, 8443);
+        assert_eq!(
+            location(redirect.clone(), "example.com:8081", "/about").await,
+            Some("https://example.com:8443/about".to_owned())
+        );
+        assert_eq!(location(redirect, "example.com:8443", "/about").await, None);
+    }
+}
@@ -1,61 +1,337 @@
 use crate::actor::{sessions, Index};
+use crate::config::CONFIG;
 use actix_web::web::Bytes;
 use actix_web::{rt, Error};
 use chashmap::CHashMap;
 use futures::Stream;
-use moonlight::SessionId;
+use moonlight::{CorId, ServerError, SessionId};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::task::{Context, Poll};
+use std::task::{Context, Poll, Waker};
 use std::time::Duration;
-use tokio::sync::mpsc::{error::SendError, unbounded_channel, UnboundedReceiver, UnboundedSender};
-use tokio::time::{interval_at, Instant};
+use std::{error, fmt};
+use tokio::sync::Notify;
+use tokio::time::{interval_at, sleep, Instant};
+
+// How long a disconnected session may be resumed, e.g. by a page reload.
+const SESSION_RESUMPTION_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub type ShareableSSE = Arc<SSE>;
 
+// ------ OverflowPolicy ------
+
+/// What to do when a connection's queue is full (e.g. a client on a stalled network).
+///
+/// Responses to `UpMsg`s (`down_msg`, `down_msg_stream` and `down_msg_stream_end` events)
+/// are never dropped or coalesced. When only responses are queued, the connection is closed
+/// and zoon receives `ServerError`s for the lost responses after it reconnects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued message.
+    DropOldest,
+    /// Replace the oldest queued message with the same event type,
+    /// or drop the oldest message when there is no such message.
+    Coalesce,
+    /// Close the connection. The client reconnects and continues with an empty queue.
+    Disconnect,
+}
+
+impl Default for OverflowPolicy {
+    fn default() -> Self {
+        Self::DropOldest
+    }
+}
+
+// ------ SSEMetrics ------
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SSEMetrics {
+    pub connections: usize,
+    pub queued_messages: usize,
+    pub max_queue_depth: usize,
+    pub dropped_messages: u64,
+    pub evicted_connections: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    dropped_messages: AtomicU64,
+    evicted_connections: AtomicU64,
+}
+
+// ------ SendError ------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendError {
+    Disconnected,
+    Evicted,
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disconnected => write!(f, "SSE connection is closed"),
+            Self::Evicted => write!(
+                f,
+                "SSE connection has been closed because its queue is full"
+            ),
+        }
+    }
+}
+
+impl error::Error for SendError {}
+
+// ------ Queue ------
+
+struct Queue {
+    state: Mutex<QueueState>,
+    capacity: usize,
+    overflow_policy: OverflowPolicy,
+    space_available: Notify,
+}
+
+#[derive(Default)]
+struct QueueState {
+    messages: VecDeque<Message>,
+    waker: Option<Waker>,
+    closed: bool,
+    overflowed: bool,
+}
+
+struct Message {
+    event: String,
+    bytes: Bytes,
+    cor_id: Option<CorId>,
+}
+
+// `CorId`s of responses dropped together with evicted connections, by session.
+type LostCorIds = Arc<CHashMap<SessionId, Vec<CorId>>>;
+
 // ------ Connection ------
 
 pub struct Connection {
     remove_session_actor_on_remove: bool,
     session_id: SessionId,
-    sender: UnboundedSender<Bytes>,
+    queue: Arc<Queue>,
+    counters: Arc<Counters>,
+    lost_cor_ids: LostCorIds,
 }
 
 impl Connection {
-    fn new(session_id: Option<SessionId>) -> (Arc<Connection>, EventStream) {
-        let (sender, receiver) = unbounded_channel();
+    fn new(
+        session_id: Option<SessionId>,
+        capacity: usize,
+        overflow_policy: OverflowPolicy,
+        counters: Arc<Counters>,
+        lost_cor_ids: LostCorIds,
+    ) -> (Arc<Connection>, EventStream) {
+        let queue = Arc::new(Queue {
+            state: Mutex::default(),
+            capacity: capacity.max(1),
+            overflow_policy,
+            space_available: Notify::new(),
+        });
         let connection = Arc::new(Self {
             remove_session_actor_on_remove: session_id.is_some(),
             session_id: session_id.unwrap_or_else(SessionId::new),
-            sender,
+            queue: Arc::clone(&queue),
+            counters,
+            lost_cor_ids,
         });
-        (connection, EventStream(receiver))
+        (connection, EventStream(queue))
     }
 
     fn session_id(&self) -> SessionId {
         self.session_id
     }
 
-    pub fn send(&self, event: &str, data: &str) -> Result<(), SendError<Bytes>> {
-        let message = Bytes::from(["event: ", event, "\n", "data: ", data, "\n\n"].concat());
-        self.sender.send(message)
+    pub fn queue_depth(&self) -> usize {
+        self.queue.state.lock().messages.len()
+    }
+
+    /// Ends the event stream and thus the related HTTP response.
+    pub fn close(&self) {
+        let mut state = self.queue.state.lock();
+        state.closed = true;
+        state.messages.clear();
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    pub fn send(&self, event: &str, data: &str) -> Result<(), SendError> {
+        self.push(event, data, None)
+    }
+
+    /// Sends a response to the `UpMsg` with the given `CorId`.
+    /// The response is never dropped, see [`OverflowPolicy`].
+    pub fn send_correlated(&self, event: &str, data: &str, cor_id: CorId) -> Result<(), SendError> {
+        self.push(event, data, Some(cor_id))
+    }
+
+    fn push(&self, event: &str, data: &str, cor_id: Option<CorId>) -> Result<(), SendError> {
+        let mut state = self.queue.state.lock();
+        if state.closed {
+            return Err(SendError::Disconnected);
+        }
+        // Already queued messages are enough to detect a closed connection.
+        if event == "ping" && !state.messages.is_empty() {
+            return Ok(());
+        }
+
+        if state.messages.len() >= self.queue.capacity {
+            if !state.overflowed {
+                state.overflowed = true;
+                log::warn!(
+                    "SSE queue of session '{}' is full ({} messages), applying {:?}",
+                    self.session_id,
+                    state.messages.len(),
+                    self.queue.overflow_policy,
+                );
+            }
+            let droppable_index = match self.queue.overflow_policy {
+                OverflowPolicy::DropOldest => state
+                    .messages
+                    .iter()
+                    .position(|message| message.cor_id.is_none()),
+                // Only messages of the same type without a `CorId` may be merged.
+                OverflowPolicy::Coalesce => cor_id
+                    .is_none()
+                    .then(|| {
+                        state
+                            .messages
+                            .iter()
+                            .position(|message| message.cor_id.is_none() && message.event == event)
+                    })
+                    .flatten()
+                    .or_else(|| {
+                        state
+                            .messages
+                            .iter()
+                            .position(|message| message.cor_id.is_none())
+                    }),
+                OverflowPolicy::Disconnect => None,
+            };
+            match droppable_index {
+                Some(index) => {
+                    state.messages.remove(index);
+                }
+                // The new message isn't important enough to evict the connection.
+                None if cor_id.is_none()
+                    && self.queue.overflow_policy != OverflowPolicy::Disconnect =>
+                {
+                    self.counters
+                        .dropped_messages
+                        .fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                None => {
+                    let lost_cor_ids = state
+                        .messages
+                        .drain(..)
+                        .filter_map(|message| message.cor_id)
+                        .chain(cor_id)
+                        .collect();
+                    state.closed = true;
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    drop(state);
+                    self.evicted(lost_cor_ids);
+                    return Err(SendError::Evicted);
+                }
+            }
+            self.counters
+                .dropped_messages
+                .fetch_add(1, Ordering::Relaxed);
+        }
+
+        let bytes = Bytes::from(["event: ", event, "\n", "data: ", data, "\n\n"].concat());
+        state.messages.push_back(Message {
+            event: event.to_owned(),
+            bytes,
+            cor_id,
+        });
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    fn evicted(&self, mut lost_cor_ids: Vec<CorId>) {
+        self.counters
+            .evicted_connections
+            .fetch_add(1, Ordering::Relaxed);
+        self.queue.space_available.notify_waiters();
+        // Only client-provided sessions can be resumed by a new connection.
+        if !self.remove_session_actor_on_remove {
+            return;
+        }
+        if lost_cor_ids.is_empty() {
+            return;
+        }
+        self.lost_cor_ids.alter(self.session_id, |cor_ids| {
+            let mut cor_ids = cor_ids.unwrap_or_default();
+            cor_ids.append(&mut lost_cor_ids);
+            cor_ids.sort_unstable();
+            cor_ids.dedup();
+            Some(cor_ids)
+        });
+    }
+
+    /// Waits until the queue isn't full to not drop or coalesce messages sent right after.
+    pub async fn wait_for_capacity(&self) {
+        loop {
+            let space_available = self.queue.space_available.notified();
+            {
+                let state = self.queue.state.lock();
+                if state.closed || state.messages.len() < self.queue.capacity {
+                    return;
+                }
+            }
+            space_available.await;
+        }
     }
 }
 
 // ------ EventStream ------
 
-pub struct EventStream(UnboundedReceiver<Bytes>);
+pub struct EventStream(Arc<Queue>);
 
 impl Stream for EventStream {
     type Item = Result<Bytes, Error>;
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match Pin::new(&mut self.0).poll_recv(cx) {
-            Poll::Ready(Some(bytes)) => Poll::Ready(Some(Ok(bytes))),
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Pending => Poll::Pending,
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.0.state.lock();
+        if let Some(message) = state.messages.pop_front() {
+            if state.messages.is_empty() {
+                state.overflowed = false;
+            }
+            drop(state);
+            self.0.space_available.notify_waiters();
+            return Poll::Ready(Some(Ok(message.bytes)));
+        }
+        if state.closed {
+            return Poll::Ready(None);
         }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock();
+        state.closed = true;
+        state.messages.clear();
+        drop(state);
+        self.0.space_available.notify_waiters();
     }
 }
 
@@ -63,17 +339,55 @@ impl Stream for EventStream {
 
 pub struct SSE {
     connections: CHashMap<SessionId, Arc<Connection>>,
+    queue_capacity: usize,
+    overflow_policy: OverflowPolicy,
+    counters: Arc<Counters>,
+    lost_cor_ids: LostCorIds,
 }
 
 impl SSE {
     pub fn start() -> ShareableSSE {
         let sse = SSE {
             connections: CHashMap::new(),
+            queue_capacity: CONFIG.sse.queue_capacity,
+            overflow_policy: CONFIG.sse.overflow_policy,
+            counters: Arc::default(),
+            lost_cor_ids: Arc::default(),
         };
         let this = Arc::new(sse);
         this.spawn_connection_remover();
         this
     }
+
+    pub fn metrics(&self) -> SSEMetrics {
+        let metrics = RefCell::new(SSEMetrics {
+            dropped_messages: self.counters.dropped_messages.load(Ordering::Relaxed),
+            evicted_connections: self.counters.evicted_connections.load(Ordering::Relaxed),
+            ..SSEMetrics::default()
+        });
+        self.connections.retain(|_, connection| {
+            let queue_depth = connection.queue_depth();
+            let mut metrics = metrics.borrow_mut();
+            metrics.connections += 1;
+            metrics.queued_messages += queue_depth;
+            metrics.max_queue_depth = metrics.max_queue_depth.max(queue_depth);
+            true
+        });
+        metrics.into_inner()
+    }
+}
+
+fn remove_session_actor_unless_resumed(sse: &ShareableSSE, session_id: SessionId) {
+    let sse = Arc::clone(sse);
+    rt::spawn(async move {
+        sleep(SESSION_RESUMPTION_TIMEOUT).await;
+        if sse.connection(&session_id).is_none() {
+            sse.lost_cor_ids.remove(&session_id);
+            if let Some(session_actor) = sessions::by_session_id().get(session_id) {
+                session_actor.remove();
+            }
+        }
+    });
 }
 
 // ------ ShareableSSEMethods ------
@@ -83,14 +397,24 @@ pub trait ShareableSSEMethods {
 
     fn new_connection(&self, session_id: Option<SessionId>) -> (Arc<Connection>, EventStream);
 
-    fn broadcast(&self, event: &str, data: &str) -> Result<(), Vec<SendError<Bytes>>>;
+    fn connection(&self, session_id: &SessionId) -> Option<Arc<Connection>>;
+
+    fn broadcast(&self, event: &str, data: &str) -> Result<(), Vec<SendError>>;
 
     fn send(
         &self,
         session_id: &SessionId,
         event: &str,
         data: &str,
-    ) -> Option<Result<(), SendError<Bytes>>>;
+    ) -> Option<Result<(), SendError>>;
+
+    fn send_correlated(
+        &self,
+        session_id: &SessionId,
+        event: &str,
+        data: &str,
+        cor_id: CorId,
+    ) -> Option<Result<(), SendError>>;
 
     fn remove_connection(&self, session_id: &SessionId);
 }
@@ -105,24 +429,56 @@ impl ShareableSSEMethods for ShareableSSE {
                 this.connections.retain(|session_id, connection| {
                     let active = connection.send("ping", "").is_ok();
                     if !active && connection.remove_session_actor_on_remove {
-                        if let Some(session_actor) = sessions::by_session_id().get(session_id) {
-                            session_actor.remove();
-                        }
+                        remove_session_actor_unless_resumed(&this, *session_id);
                     }
                     active
                 });
+
+                let metrics = this.metrics();
+                if metrics.max_queue_depth > this.queue_capacity / 2 {
+                    log::warn!("SSE queues are filling up: {:?}", metrics);
+                } else {
+                    log::debug!("SSE: {:?}", metrics);
+                }
             }
         });
     }
 
     fn new_connection(&self, session_id: Option<SessionId>) -> (Arc<Connection>, EventStream) {
-        let (connection, event_stream) = Connection::new(session_id);
-        self.connections
-            .insert(connection.session_id(), connection.clone());
+        let (connection, event_stream) = Connection::new(
+            session_id,
+            self.queue_capacity,
+            self.overflow_policy,
+            Arc::clone(&self.counters),
+            Arc::clone(&self.lost_cor_ids),
+        );
+        // The previous connection of a resumed session is replaced.
+        if let Some(previous_connection) = self
+            .connections
+            .insert(connection.session_id(), connection.clone())
+        {
+            previous_connection.close();
+        }
+        // The responses lost with the evicted connection would never arrive.
+        if let Some(lost_cor_ids) = self.lost_cor_ids.remove(&connection.session_id()) {
+            for cor_id in lost_cor_ids {
+                let error = ServerError::Internal(
+                    "the response has been dropped because the SSE queue was full".to_owned(),
+                );
+                let transporter = sessions::serialize_server_error(error, cor_id);
+                let _ = connection.send_correlated("down_msg", &transporter, cor_id);
+            }
+        }
         (connection, event_stream)
     }
 
-    fn broadcast(&self, event: &str, data: &str) -> Result<(), Vec<SendError<Bytes>>> {
+    fn connection(&self, session_id: &SessionId) -> Option<Arc<Connection>> {
+        self.connections
+            .get(session_id)
+            .map(|connection| Arc::clone(&connection))
+    }
+
+    fn broadcast(&self, event: &str, data: &str) -> Result<(), Vec<SendError>> {
         let errors = RefCell::new(Vec::new());
         self.connections.retain(|_, connection| {
             if let Err(error) = connection.send(event, data) {
@@ -142,17 +498,31 @@ impl ShareableSSEMethods for ShareableSSE {
         session_id: &SessionId,
         event: &str,
         data: &str,
-    ) -> Option<Result<(), SendError<Bytes>>> {
+    ) -> Option<Result<(), SendError>> {
         // @TODO Last-Event-Id
         self.connections
             .get(session_id)
             .map(|connection| connection.send(event, data))
     }
 
+    fn send_correlated(
+        &self,
+        session_id: &SessionId,
+        event: &str,
+        data: &str,
+        cor_id: CorId,
+    ) -> Option<Result<(), SendError>> {
+        self.connections
+            .get(session_id)
+            .map(|connection| connection.send_correlated(event, data, cor_id))
+    }
+
     fn remove_connection(&self, session_id: &SessionId) {
         let connection = self.connections.remove(session_id);
 
+        self.lost_cor_ids.remove(session_id);
         if let Some(connection) = connection {
+            connection.close();
             if connection.remove_session_actor_on_remove {
                 if let Some(session_actor) = sessions::by_session_id().get(session_id) {
                     session_actor.remove();
@@ -161,3 +531,135 @@ impl ShareableSSEMethods for ShareableSSE {
         }
     }
 }
+
+// ====== ====== TESTS ====== ======
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::rt as actix_rt;
+    use futures::StreamExt;
+
+    fn connection(capacity: usize, policy: OverflowPolicy) -> (Arc<Connection>, EventStream) {
+        Connection::new(None, capacity, policy, Arc::default(), Arc::default())
+    }
+
+    async fn events(event_stream: &mut EventStream, count: usize) -> Vec<Bytes> {
+        let mut events = Vec::new();
+        for _ in 0..count {
+            events.push(event_stream.next().await.unwrap().unwrap());
+        }
+        events
+    }
+
+    #[actix_rt::test]
+    async fn test_drop_oldest() {
+        let (connection, mut event_stream) = connection(2, OverflowPolicy::DropOldest);
+        connection.send("a", "1").unwrap();
+        connection.send("a", "2").unwrap();
+        connection.send("a", "3").unwrap();
+
+        assert_eq!(
+            connection.counters.dropped_messages.load(Ordering::Relaxed),
+            1
+        );
+        assert_eq!(
+            events(&mut event_stream, 2).await,
+            ["event: a\ndata: 2\n\n", "event: a\ndata: 3\n\n"]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_coalesce() {
+        let (connection, mut event_stream) = connection(2, OverflowPolicy::Coalesce);
+        connection.send("a", "1").unwrap();
+        connection.send("b", "1").unwrap();
+        connection.send("b", "2").unwrap();
+
+        assert_eq!(
+            events(&mut event_stream, 2).await,
+            ["event: a\ndata: 1\n\n", "event: b\ndata: 2\n\n"]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_disconnect() {
+        let (connection, mut event_stream) = connection(1, OverflowPolicy::Disconnect);
+        connection.send("a", "1").unwrap();
+
+        assert_eq!(connection.send("a", "2"), Err(SendError::Evicted));
+        assert_eq!(connection.send("a", "3"), Err(SendError::Disconnected));
+        assert!(event_stream.next().await.is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_responses_are_not_dropped() {
+        let (connection, mut event_stream) = connection(2, OverflowPolicy::DropOldest);
+        let cor_id = CorId::new();
+        connection.send_correlated("down_msg", "1", cor_id).unwrap();
+        connection.send("a", "1").unwrap();
+        connection.send_correlated("down_msg", "2", cor_id).unwrap();
+
+        assert_eq!(
+            events(&mut event_stream, 2).await,
+            [
+                "event: down_msg\ndata: 1\n\n",
+                "event: down_msg\ndata: 2\n\n"
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_coalesce_doesnt_merge_responses() {
+        let (connection, mut event_stream) = connection(2, OverflowPolicy::Coalesce);
+        connection
+            .send_correlated("down_msg", "1", CorId::new())
+            .unwrap();
+        connection.send("a", "1").unwrap();
+        connection
+            .send_correlated("down_msg", "2", CorId::new())
+            .unwrap();
+
+        assert_eq!(
+            events(&mut event_stream, 2).await,
+            [
+                "event: down_msg\ndata: 1\n\n",
+                "event: down_msg\ndata: 2\n\n"
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_evicted_responses_are_remembered() {
+        let session_id = SessionId::new();
+        let lost_cor_ids = LostCorIds::default();
+        let (connection, mut event_stream) = Connection::new(
+            Some(session_id),
+            2,
+            OverflowPolicy::DropOldest,
+            Arc::default(),
+            Arc::clone(&lost_cor_ids),
+        );
+        let (first_cor_id, second_cor_id) = (CorId::new(), CorId::new());
+        connection
+            .send_correlated("down_msg_stream", "1", first_cor_id)
+            .unwrap();
+        connection
+            .send_correlated("down_msg_stream", "2", first_cor_id)
+            .unwrap();
+        // Only responses are queued, the unimportant message is dropped instead.
+        connection.send("a", "1").unwrap();
+
+        assert_eq!(
+            connection.send_correlated("down_msg", "3", second_cor_id),
+            Err(SendError::Evicted)
+        );
+        assert!(event_stream.next().await.is_none());
+        let mut expected_cor_ids = vec![first_cor_id, second_cor_id];
+        expected_cor_ids.sort_unstable();
+        assert_eq!(
+            lost_cor_ids.get(&session_id).as_deref(),
+            Some(&expected_cor_ids)
+        );
+    }
+}
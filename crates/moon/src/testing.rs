@@ -0,0 +1,295 @@
+//! In-process test client for Moon apps.
+//!
+//! ```ignore
+//! #[moon::test]
+//! async fn add_message() {
+//!     let app = TestApp::new(frontend, up_msg_handler, |_| {}).await;
+//!     let mut alice = app.session().await;
+//!     let mut bob = app.session().await;
+//!
+//!     let cor_id = alice.send_up_msg(&UpMsg::SendMessage(message.clone())).await;
+//!     assert_eq!(alice.down_msg::<DownMsg>(cor_id).await, DownMsg::MessageReceived(message.clone()));
+//!     assert_eq!(bob.down_msg::<DownMsg>(cor_id).await, DownMsg::MessageReceived(message));
+//! }
+//! ```
+//!
+//! Helpers panic on failures so they can be used directly in tests.
+
+use crate::*;
+use actix_http::Request;
+use actix_web::{dev::Service, test, web::Bytes};
+use futures::{
+    future::LocalBoxFuture,
+    stream::{self, LocalBoxStream},
+};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Duration;
+
+type CallService = Rc<dyn Fn(Request) -> LocalBoxFuture<'static, Result<ServiceResponse, Error>>>;
+
+// ------ TestApp ------
+
+pub struct TestApp {
+    call_service: CallService,
+    down_msg_timeout: Duration,
+}
+
+impl TestApp {
+    /// Creates the app from the same arguments as [`start`](crate::start).
+    /// The server isn't bound to any port, all requests are handled in-process.
+    pub async fn new<FRB, FRBO, UPH, UPHO, UMsg>(
+        frontend: FRB,
+        up_msg_handler: UPH,
+        service_config: impl Fn(&mut web::ServiceConfig) + Send + Sync + 'static,
+    ) -> Self
+    where
+        FRB: FrontBuilder<FRBO>,
+        FRBO: FrontBuilderOutput,
+        UPH: UpHandler<UPHO, UMsg>,
+        UPHO: UpHandlerOutput,
+        UMsg: 'static + DeserializeOwned,
+    {
+        let shared_data = SharedData {
+            backend_build_id: u128::default(),
+            frontend_build_id: u128::default(),
+            cache_busting: false,
+            compressed_pkg: false,
+            compressed_public: false,
+            pkg_path: "frontend/pkg",
+            public_path: "public",
+            compressed_public_path: "frontend/pkg/public",
+        };
+        let app = App::new()
+            .app_data(web::Data::new(shared_data))
+            .app_data(web::Data::new(frontend))
+            .app_data(web::Data::new(up_msg_handler))
+            .app_data(web::Data::new(ReloadSSE(SSE::start())))
+            .app_data(web::Data::new(MessageSSE(SSE::start())))
+            .configure(service_config)
+            .service(api_scope::<UPH, UPHO, UMsg>())
+            .default_service(web::get().to(frontend_responder::<FRB, FRBO>))
+            .wrap(version_header());
+
+        let service = Rc::new(test::init_service(app).await);
+        let call_service: CallService = Rc::new(move |request| {
+            let service = Rc::clone(&service);
+            Box::pin(async move {
+                service
+                    .call(request)
+                    .await
+                    .map(ServiceResponse::map_into_boxed_body)
+            })
+        });
+        Self {
+            call_service,
+            down_msg_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// How long `TestSession` waits for a `DownMsg` before it panics. The default is 5 seconds.
+    pub fn down_msg_timeout(mut self, timeout: Duration) -> Self {
+        self.down_msg_timeout = timeout;
+        self
+    }
+
+    /// Sends a raw request, e.g. to test routes registered in `service_config`.
+    pub async fn call(&self, request: Request) -> ServiceResponse {
+        (self.call_service)(request)
+            .await
+            .unwrap_or_else(|error| panic!("request failed: {error}"))
+    }
+
+    /// Opens a new simulated session subscribed to the message SSE stream.
+    pub async fn session(&self) -> TestSession<'_> {
+        self.session_with_id(SessionId::new()).await
+    }
+
+    /// Simulates a reloaded page or a reconnected `EventSource`.
+    pub async fn session_with_id(&self, session_id: SessionId) -> TestSession<'_> {
+        let request = test::TestRequest::get()
+            .uri(&format!("/_api/message_sse/{session_id}"))
+            .to_request();
+        let response = self.call(request).await;
+        assert!(
+            response.status().is_success(),
+            "message SSE connection failed with status {}",
+            response.status()
+        );
+
+        let mut body = response.into_body();
+        let events = stream::poll_fn(move |cx| Pin::new(&mut body).poll_next(cx))
+            .filter_map(|chunk| async move { chunk.ok() })
+            .boxed_local();
+
+        TestSession {
+            app: self,
+            session_id,
+            auth_token: None,
+            events,
+            buffer: String::new(),
+            down_msgs: VecDeque::new(),
+        }
+    }
+}
+
+// ------ TestSession ------
+
+pub struct TestSession<'a> {
+    app: &'a TestApp,
+    session_id: SessionId,
+    auth_token: Option<AuthToken>,
+    events: LocalBoxStream<'static, Bytes>,
+    buffer: String,
+    // Received but not yet consumed `DownMsgTransporter`s.
+    down_msgs: VecDeque<(CorId, String)>,
+}
+
+impl TestSession<'_> {
+    pub fn session_id(&self) -> SessionId {
+        self.session_id
+    }
+
+    /// The token is sent with all following `UpMsg`s.
+    pub fn auth_token(mut self, auth_token: impl Into<Option<AuthToken>>) -> Self {
+        self.auth_token = auth_token.into();
+        self
+    }
+
+    pub async fn send_up_msg<UMsg: Serialize>(&self, up_msg: &UMsg) -> CorId {
+        let cor_id = CorId::new();
+        self.send_up_msg_with_cor_id(up_msg, cor_id).await;
+        cor_id
+    }
+
+    /// Reuse the `CorId` to simulate a retried request.
+    pub async fn send_up_msg_with_cor_id<UMsg: Serialize>(&self, up_msg: &UMsg, cor_id: CorId) {
+        #[cfg(feature = "serde-lite")]
+        let body = serde_json::to_string(&up_msg.serialize().unwrap()).unwrap();
+        #[cfg(feature = "serde")]
+        let body = serde_json::to_string(up_msg).unwrap();
+
+        let mut request = test::TestRequest::post()
+            .uri("/_api/up_msg_handler")
+            .insert_header(("X-Session-ID", self.session_id.to_string()))
+            .insert_header(("X-Correlation-ID", cor_id.to_string()))
+            .set_payload(body);
+        if let Some(auth_token) = &self.auth_token {
+            request = request.insert_header(("X-Auth-Token", auth_token.as_str()));
+        }
+
+        let response = self.app.call(request.to_request()).await;
+        assert!(
+            response.status().is_success(),
+            "UpMsg request failed with status {}",
+            response.status()
+        );
+    }
+
+    /// Simulates a cancelled `exchange_msgs` call in zoon.
+    pub async fn cancel_up_msg(&self, cor_id: CorId) {
+        let request = test::TestRequest::post()
+            .uri("/_api/cancel_up_msg")
+            .insert_header(("X-Session-ID", self.session_id.to_string()))
+            .insert_header(("X-Correlation-ID", cor_id.to_string()))
+            .to_request();
+        let response = self.app.call(request).await;
+        assert!(
+            response.status().is_success(),
+            "cancel request failed with status {}",
+            response.status()
+        );
+    }
+
+    /// Waits for the `DownMsg` or `ServerError` with the given `CorId`.
+    /// Messages with other `CorId`s are kept for later calls.
+    pub async fn down_msg_result<DMsg: DeserializeOwned>(
+        &mut self,
+        cor_id: CorId,
+    ) -> Result<DMsg, ServerError> {
+        loop {
+            if let Some(index) = self.down_msgs.iter().position(|(id, _)| *id == cor_id) {
+                let (_, transporter) = self.down_msgs.remove(index).unwrap();
+                return deserialize_down_msg_transporter::<DMsg>(&transporter).into_result();
+            }
+            self.receive_events().await;
+        }
+    }
+
+    /// Waits for the `DownMsg` with the given `CorId`.
+    /// Panics when Moon sent a `ServerError` instead.
+    pub async fn down_msg<DMsg: DeserializeOwned>(&mut self, cor_id: CorId) -> DMsg {
+        self.down_msg_result(cor_id)
+            .await
+            .unwrap_or_else(|error| panic!("server error received for '{cor_id}': {error}"))
+    }
+
+    /// Waits for the oldest not yet consumed `DownMsg`.
+    pub async fn next_down_msg<DMsg: DeserializeOwned>(&mut self) -> (DMsg, CorId) {
+        loop {
+            if let Some((cor_id, transporter)) = self.down_msgs.pop_front() {
+                let down_msg = deserialize_down_msg_transporter::<DMsg>(&transporter)
+                    .into_result()
+                    .unwrap_or_else(|error| {
+                        panic!("server error received for '{cor_id}': {error}")
+                    });
+                return (down_msg, cor_id);
+            }
+            self.receive_events().await;
+        }
+    }
+
+    /// Waits for the error sent instead of a `DownMsg` when Moon failed to handle the `UpMsg`.
+    pub async fn server_error(&mut self, cor_id: CorId) -> ServerError {
+        match self.down_msg_result::<serde_json::Value>(cor_id).await {
+            Ok(down_msg) => panic!("DownMsg received instead of a server error: {down_msg}"),
+            Err(error) => error,
+        }
+    }
+
+    pub async fn exchange_msgs<UMsg: Serialize, DMsg: DeserializeOwned>(
+        &mut self,
+        up_msg: &UMsg,
+    ) -> DMsg {
+        let cor_id = self.send_up_msg(up_msg).await;
+        self.down_msg(cor_id).await
+    }
+
+    async fn receive_events(&mut self) {
+        let chunk = actix_web::rt::time::timeout(self.app.down_msg_timeout, self.events.next())
+            .await
+            .unwrap_or_else(|_| {
+                panic!(
+                    "no message received by session '{}' in {:?}",
+                    self.session_id, self.app.down_msg_timeout
+                )
+            })
+            .expect("message SSE stream closed");
+        self.buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+        while let Some(index) = self.buffer.find("\n\n") {
+            let event = self.buffer[..index].to_owned();
+            self.buffer.drain(..index + 2);
+            if let Some(("down_msg", transporter)) = event_name_and_data(&event) {
+                let cor_id =
+                    deserialize_down_msg_transporter::<serde_json::Value>(transporter).cor_id();
+                self.down_msgs.push_back((cor_id, transporter.to_owned()));
+            }
+        }
+    }
+}
+
+fn event_name_and_data(event: &str) -> Option<(&str, &str)> {
+    let mut lines = event.lines();
+    let name = lines.next()?.strip_prefix("event: ")?;
+    let data = lines.next()?.strip_prefix("data: ")?;
+    Some((name, data))
+}
+
+fn deserialize_down_msg_transporter<DMsg: DeserializeOwned>(
+    transporter: &str,
+) -> DownMsgTransporterForDe<DMsg> {
+    serde_json::from_str(transporter)
+        .unwrap_or_else(|error| panic!("failed to deserialize DownMsgTransporter: {error}"))
+}
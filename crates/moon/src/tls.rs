@@ -0,0 +1,305 @@
+use crate::config::Tls;
+use actix_web::rt;
+use parking_lot::RwLock;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::{any_supported_type, CertifiedKey};
+use rustls::{Certificate, PrivateKey, ServerConfig as RustlsServerConfig};
+use rustls_pemfile::{certs, pkcs8_private_keys};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::time::{interval_at, Instant};
+
+const PRIVATE_DIR: &str = "backend/private";
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// ------ rustls_server_config ------
+
+pub fn rustls_server_config(config: &Tls) -> io::Result<RustlsServerConfig> {
+    let cert_resolver = Arc::new(CertResolver::new(config)?);
+    if config.reload {
+        cert_resolver.spawn_reloader();
+    }
+    let config = RustlsServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(cert_resolver);
+    Ok(config)
+}
+
+// ------ CertKeyPaths ------
+
+struct CertKeyPaths {
+    // `None` for the default certificate
+    host: Option<String>,
+    cert: PathBuf,
+    key: PathBuf,
+}
+
+impl CertKeyPaths {
+    fn default_pair(private_dir: &Path) -> Self {
+        Self {
+            host: None,
+            cert: private_dir.join("public.pem"),
+            key: private_dir.join("private.pem"),
+        }
+    }
+
+    fn sni_pair(private_dir: &Path, host: &str) -> Self {
+        let dir = private_dir.join(host);
+        Self {
+            host: Some(host.to_owned()),
+            cert: dir.join("public.pem"),
+            key: dir.join("private.pem"),
+        }
+    }
+
+    fn modified(&self) -> Option<(SystemTime, SystemTime)> {
+        let modified = |path: &PathBuf| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+        Some((modified(&self.cert)?, modified(&self.key)?))
+    }
+
+    fn load(&self) -> io::Result<Arc<CertifiedKey>> {
+        let key_file = &mut BufReader::new(open(&self.key)?);
+        let key = pkcs8_private_keys(key_file)
+            .map_err(|error| invalid_data(format!("cannot parse {:?}: {error}", self.key)))?
+            .into_iter()
+            .map(PrivateKey)
+            .next()
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "private key file {:?} has to contain at least one PKCS8 key",
+                    self.key
+                ))
+            })?;
+        let signing_key = any_supported_type(&key)
+            .map_err(|_| invalid_data(format!("private key in {:?} is invalid", self.key)))?;
+
+        let cert_file = &mut BufReader::new(open(&self.cert)?);
+        let certificates = certs(cert_file)
+            .map_err(|error| invalid_data(format!("cannot parse {:?}: {error}", self.cert)))?
+            .into_iter()
+            .map(Certificate)
+            .collect::<Vec<_>>();
+        if certificates.is_empty() {
+            Err(invalid_data(format!(
+                "certificate file {:?} has to contain at least one certificate",
+                self.cert
+            )))?
+        }
+        Ok(Arc::new(CertifiedKey::new(certificates, signing_key)))
+    }
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    File::open(path)
+        .map_err(|error| io::Error::new(error.kind(), format!("cannot open {path:?}: {error}")))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// ------ CertifiedKeys ------
+
+struct CertifiedKeys {
+    default: Arc<CertifiedKey>,
+    by_host: BTreeMap<String, Arc<CertifiedKey>>,
+}
+
+impl CertifiedKeys {
+    fn load(paths: &[CertKeyPaths]) -> io::Result<Self> {
+        let mut default = None;
+        let mut by_host = BTreeMap::new();
+        for cert_key_paths in paths {
+            let certified_key = cert_key_paths.load()?;
+            match &cert_key_paths.host {
+                Some(host) => {
+                    by_host.insert(host.to_ascii_lowercase(), certified_key);
+                }
+                None => default = Some(certified_key),
+            }
+        }
+        Ok(Self {
+            default: default.expect("default certificate paths are always present"),
+            by_host,
+        })
+    }
+}
+
+// ------ CertResolver ------
+
+struct CertResolver {
+    paths: Vec<CertKeyPaths>,
+    certified_keys: RwLock<CertifiedKeys>,
+}
+
+impl CertResolver {
+    fn new(config: &Tls) -> io::Result<Self> {
+        Self::with_private_dir(config, Path::new(PRIVATE_DIR))
+    }
+
+    fn with_private_dir(config: &Tls, private_dir: &Path) -> io::Result<Self> {
+        let paths = config
+            .sni_hosts
+            .iter()
+            .map(|host| CertKeyPaths::sni_pair(private_dir, host))
+            .chain([CertKeyPaths::default_pair(private_dir)])
+            .collect::<Vec<_>>();
+        let certified_keys = CertifiedKeys::load(&paths)?;
+        Ok(Self {
+            paths,
+            certified_keys: RwLock::new(certified_keys),
+        })
+    }
+
+    fn modified(&self) -> Vec<Option<(SystemTime, SystemTime)>> {
+        self.paths.iter().map(CertKeyPaths::modified).collect()
+    }
+
+    // The old certificates are kept when the new ones are invalid
+    // or only partially written.
+    fn reload(&self) -> io::Result<()> {
+        let certified_keys = CertifiedKeys::load(&self.paths)?;
+        *self.certified_keys.write() = certified_keys;
+        Ok(())
+    }
+
+    fn certified_key(&self, host: Option<&str>) -> Arc<CertifiedKey> {
+        let certified_keys = self.certified_keys.read();
+        let certified_key = host
+            .and_then(|host| certified_keys.by_host.get(&host.to_ascii_lowercase()))
+            .unwrap_or(&certified_keys.default);
+        Arc::clone(certified_key)
+    }
+
+    fn spawn_reloader(self: &Arc<Self>) {
+        let this = Arc::clone(self);
+        rt::spawn(async move {
+            let mut last_modified = this.modified();
+            let mut interval = interval_at(
+                Instant::now() + RELOAD_CHECK_INTERVAL,
+                RELOAD_CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let modified = this.modified();
+                if modified == last_modified {
+                    continue;
+                }
+                match this.reload() {
+                    Ok(()) => {
+                        last_modified = modified;
+                        println!("TLS certificates reloaded");
+                    }
+                    Err(error) => eprintln!("TLS certificates reload failed: {error}"),
+                }
+            }
+        });
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.certified_key(client_hello.server_name()))
+    }
+}
+
+// ====== ====== TESTS ====== ======
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+
+    fn tls_config(sni_hosts: &[&'static str]) -> Tls {
+        Tls {
+            reload: false,
+            sni_hosts: sni_hosts.iter().map(|host| (*host).into()).collect(),
+        }
+    }
+
+    fn fixture_cert(path: &str) -> Certificate {
+        let cert_file = &mut BufReader::new(File::open(format!("{FIXTURES_DIR}/{path}")).unwrap());
+        Certificate(certs(cert_file).unwrap().remove(0))
+    }
+
+    #[test]
+    fn test_sni() {
+        let resolver =
+            CertResolver::with_private_dir(&tls_config(&["example.com"]), Path::new(FIXTURES_DIR))
+                .unwrap();
+
+        let example_cert = fixture_cert("example.com/public.pem");
+        assert_eq!(
+            resolver.certified_key(Some("example.com")).cert,
+            [example_cert.clone()]
+        );
+        assert_eq!(
+            resolver.certified_key(Some("EXAMPLE.com")).cert,
+            [example_cert]
+        );
+    }
+
+    #[test]
+    fn test_default_fallback() {
+        let resolver =
+            CertResolver::with_private_dir(&tls_config(&["example.com"]), Path::new(FIXTURES_DIR))
+                .unwrap();
+
+        let default_cert = fixture_cert("public.pem");
+        assert_eq!(resolver.certified_key(None).cert, [default_cert.clone()]);
+        assert_eq!(
+            resolver.certified_key(Some("unknown.com")).cert,
+            [default_cert]
+        );
+    }
+
+    #[test]
+    fn test_invalid_reload() {
+        let private_dir = std::env::temp_dir().join(format!("moon_tls_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&private_dir).unwrap();
+        for file in ["public.pem", "private.pem"] {
+            fs::copy(format!("{FIXTURES_DIR}/{file}"), private_dir.join(file)).unwrap();
+        }
+        let resolver = CertResolver::with_private_dir(&tls_config(&[]), &private_dir).unwrap();
+
+        // E.g. a certificate renewal tool has written only a part of the file.
+        fs::write(
+            private_dir.join("public.pem"),
+            "-----BEGIN CERTIFICATE-----\nMIIB",
+        )
+        .unwrap();
+        assert!(resolver.reload().is_err());
+        assert_eq!(
+            resolver.certified_key(None).cert,
+            [fixture_cert("public.pem")]
+        );
+
+        fs::copy(
+            format!("{FIXTURES_DIR}/example.com/public.pem"),
+            private_dir.join("public.pem"),
+        )
+        .unwrap();
+        fs::copy(
+            format!("{FIXTURES_DIR}/example.com/private.pem"),
+            private_dir.join("private.pem"),
+        )
+        .unwrap();
+        resolver.reload().unwrap();
+        assert_eq!(
+            resolver.certified_key(None).cert,
+            [fixture_cert("example.com/public.pem")]
+        );
+
+        fs::remove_dir_all(private_dir).unwrap();
+    }
+}
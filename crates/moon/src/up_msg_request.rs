@@ -1,3 +1,5 @@
+use crate::sessions;
+use futures::future;
 use moonlight::{AuthToken, CorId, SessionId};
 
 #[derive(Debug)]
@@ -7,3 +9,33 @@ pub struct UpMsgRequest<UMsg> {
     pub cor_id: CorId,
     pub auth_token: Option<AuthToken>,
 }
+
+impl<UMsg> UpMsgRequest<UMsg> {
+    /// `true` when zoon has stopped waiting for the response,
+    /// see `MsgOptions::notify_cancel` in zoon.
+    ///
+    /// `false` when the session doesn't exist,
+    /// e.g. the `UpMsg` has arrived before the SSE connection has been opened.
+    pub fn is_cancelled(&self) -> bool {
+        sessions::by_session_id()
+            .get(self.session_id)
+            .map(|session_actor| session_actor.is_cancelled(self.cor_id))
+            .unwrap_or_default()
+    }
+
+    /// Resolves when the request has been cancelled or its session has been removed.
+    /// Never resolves when the session doesn't exist, see `is_cancelled`.
+    ///
+    /// ```ignore
+    /// tokio::select! {
+    ///     report = generate_report() => send_report(report).await,
+    ///     () = req.cancelled() => println!("report generation cancelled"),
+    /// }
+    /// ```
+    pub async fn cancelled(&self) {
+        match sessions::by_session_id().get(self.session_id) {
+            Some(session_actor) => session_actor.cancelled(self.cor_id).await,
+            None => future::pending().await,
+        }
+    }
+}
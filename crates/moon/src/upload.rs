@@ -0,0 +1,270 @@
+use crate::{parse_auth_token, parse_cor_id, parse_session_id};
+use actix_multipart::Multipart;
+use actix_web::{error, web, web::Bytes, Error, HttpMessage, HttpRequest, HttpResponse, Resource};
+use futures::{Stream, StreamExt};
+use moonlight::{AuthToken, CorId, SessionId};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::{fs, io::AsyncWriteExt};
+use trait_set::trait_set;
+use uuid::Uuid;
+
+// trait aliases
+trait_set! {
+    pub trait UploadHandlerOutput = Future<Output = ()> + 'static;
+    pub trait UploadHandler<UPLHO: UploadHandlerOutput> = Fn(UploadRequest) -> UPLHO + Send + Sync + 'static;
+}
+
+// ------ route ------
+
+/// Creates the `/_api/upload` route. Register it in the `service_config` passed to `start`:
+///
+/// ```ignore
+/// |cfg: &mut web::ServiceConfig| {
+///     cfg.service(upload::route(upload_handler, UploadLimits::new().allowed_types(["image/*"])));
+/// }
+/// ```
+///
+/// The file is expected as a raw request body (`zoon::Connection::upload` sends it that way)
+/// or as the first file field of a `multipart/form-data` body, e.g. sent by an HTML form.
+pub fn route<UPLH, UPLHO>(upload_handler: UPLH, limits: UploadLimits) -> Resource
+where
+    UPLH: UploadHandler<UPLHO>,
+    UPLHO: UploadHandlerOutput,
+{
+    web::resource("_api/upload")
+        .app_data(web::Data::new(limits))
+        .app_data(web::Data::new(upload_handler))
+        .route(web::post().to(upload_responder::<UPLH, UPLHO>))
+}
+
+// ------ UploadLimits ------
+
+#[derive(Debug, Clone)]
+pub struct UploadLimits {
+    max_size: u64,
+    allowed_types: BTreeSet<String>,
+    temp_dir: PathBuf,
+}
+
+impl Default for UploadLimits {
+    fn default() -> Self {
+        Self {
+            max_size: 10 * 1_048_576,
+            allowed_types: BTreeSet::new(),
+            temp_dir: std::env::temp_dir().join("moon_uploads"),
+        }
+    }
+}
+
+impl UploadLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Max file size in bytes.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Allowed MIME types like `image/png` or `image/*`. All types are allowed by default.
+    pub fn allowed_types(mut self, types: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.allowed_types = types.into_iter().map(|type_| type_.to_string()).collect();
+        self
+    }
+
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = dir.into();
+        self
+    }
+
+    fn is_type_allowed(&self, content_type: &mime::Mime) -> bool {
+        if self.allowed_types.is_empty() {
+            return true;
+        }
+        let essence = content_type.essence_str();
+        let type_wildcard = format!("{}/*", content_type.type_());
+        self.allowed_types.contains(essence) || self.allowed_types.contains(&type_wildcard)
+    }
+}
+
+// ------ UploadRequest ------
+
+#[derive(Debug)]
+pub struct UploadRequest {
+    pub file: UploadedFile,
+    pub session_id: SessionId,
+    pub cor_id: CorId,
+    pub auth_token: Option<AuthToken>,
+}
+
+// ------ UploadedFile ------
+
+/// The temporary file is removed on drop unless it has been persisted.
+#[derive(Debug)]
+pub struct UploadedFile {
+    path: PathBuf,
+    file_name: Option<String>,
+    content_type: Option<mime::Mime>,
+    size: u64,
+    persisted: bool,
+}
+
+impl UploadedFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    pub fn content_type(&self) -> Option<&mime::Mime> {
+        self.content_type.as_ref()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Moves the uploaded file to `path`. Falls back to copying when `path` is on another file system.
+    pub async fn persist(mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if fs::rename(&self.path, path).await.is_err() {
+            fs::copy(&self.path, path).await?;
+            fs::remove_file(&self.path).await?;
+        }
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for UploadedFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+// ------ upload_responder ------
+
+#[derive(Deserialize)]
+struct UploadQuery {
+    file_name: Option<String>,
+}
+
+async fn upload_responder<UPLH, UPLHO>(
+    req: HttpRequest,
+    query: web::Query<UploadQuery>,
+    payload: web::Payload,
+    limits: web::Data<UploadLimits>,
+    upload_handler: web::Data<UPLH>,
+) -> Result<HttpResponse, Error>
+where
+    UPLH: UploadHandler<UPLHO>,
+    UPLHO: UploadHandlerOutput,
+{
+    let headers = req.headers();
+    let session_id = parse_session_id(headers)?;
+    let cor_id = parse_cor_id(headers)?;
+    let auth_token = parse_auth_token(headers)?;
+
+    let content_type = req.mime_type().map_err(error::ErrorBadRequest)?;
+    let file_name = query.into_inner().file_name;
+
+    let file = match content_type {
+        Some(content_type) if content_type.type_() == mime::MULTIPART => {
+            let mut multipart = Multipart::new(headers, payload);
+            loop {
+                let field = match multipart.next().await {
+                    Some(field) => field?,
+                    None => Err(error::ErrorBadRequest("multipart body has no file field"))?,
+                };
+                // Skip non-file fields, e.g. other form inputs.
+                let field_file_name = match field.content_disposition().get_filename() {
+                    Some(field_file_name) => field_file_name.to_owned(),
+                    None => continue,
+                };
+                // Browsers send an empty file name when no file has been selected.
+                let field_file_name = Some(field_file_name).filter(|name| !name.is_empty());
+                let content_type = field.content_type().clone();
+                check_content_type(Some(&content_type), &limits)?;
+                break write_file(
+                    field,
+                    &limits,
+                    field_file_name.or(file_name),
+                    Some(content_type),
+                )
+                .await?;
+            }
+        }
+        content_type => {
+            check_content_type(content_type.as_ref(), &limits)?;
+            let content_length = headers
+                .get(actix_web::http::header::CONTENT_LENGTH)
+                .and_then(|length| length.to_str().ok()?.parse::<u64>().ok());
+            if content_length.map_or(false, |length| length > limits.max_size) {
+                Err(error::ErrorPayloadTooLarge("file is too large"))?
+            }
+            write_file(payload, &limits, file_name, content_type).await?
+        }
+    };
+
+    let upload_request = UploadRequest {
+        file,
+        session_id,
+        cor_id,
+        auth_token,
+    };
+    upload_handler.get_ref()(upload_request).await;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn check_content_type(
+    content_type: Option<&mime::Mime>,
+    limits: &UploadLimits,
+) -> Result<(), Error> {
+    match content_type {
+        Some(content_type) if !limits.is_type_allowed(content_type) => Err(
+            error::ErrorUnsupportedMediaType(format!("file type '{content_type}' is not allowed")),
+        ),
+        None if !limits.allowed_types.is_empty() => Err(error::ErrorUnsupportedMediaType(
+            "file content type is missing",
+        )),
+        _ => Ok(()),
+    }
+}
+
+async fn write_file<E: Into<Error>>(
+    content: impl Stream<Item = Result<Bytes, E>>,
+    limits: &UploadLimits,
+    file_name: Option<String>,
+    content_type: Option<mime::Mime>,
+) -> Result<UploadedFile, Error> {
+    fs::create_dir_all(&limits.temp_dir).await?;
+    let mut file = UploadedFile {
+        path: limits.temp_dir.join(Uuid::new_v4().to_string()),
+        file_name,
+        content_type,
+        size: 0,
+        persisted: false,
+    };
+    let mut file_writer = fs::File::create(&file.path).await?;
+    futures::pin_mut!(content);
+    while let Some(chunk) = content.next().await {
+        let chunk = chunk.map_err(Into::into)?;
+        file.size += chunk.len() as u64;
+        // `file` is dropped (and removed) on error.
+        if file.size > limits.max_size {
+            Err(error::ErrorPayloadTooLarge("file is too large"))?
+        }
+        file_writer.write_all(&chunk).await?;
+    }
+    file_writer.flush().await?;
+    Ok(file)
+}
@@ -1,21 +1,83 @@
 use crate::*;
+use std::fmt;
+
+// ------ DownMsgTransporter ------
 
 #[derive(Serialize)]
-pub struct DownMsgTransporterForSer<'a, DMsg: Serialize> {
-    pub down_msg: &'a DMsg,
-    pub cor_id: CorId,
+pub enum DownMsgTransporterForSer<'a, DMsg: Serialize> {
+    Ok { down_msg: &'a DMsg, cor_id: CorId },
+    Err { error: ServerError, cor_id: CorId },
 }
 
 #[cfg(feature = "serde-lite")]
 #[derive(Deserialize)]
-pub struct DownMsgTransporterForDe<DMsg: Deserialize> {
-    pub down_msg: DMsg,
-    pub cor_id: CorId,
+pub enum DownMsgTransporterForDe<DMsg: Deserialize> {
+    Ok { down_msg: DMsg, cor_id: CorId },
+    Err { error: ServerError, cor_id: CorId },
+}
+
+#[cfg(feature = "serde-lite")]
+impl<DMsg: Deserialize> DownMsgTransporterForDe<DMsg> {
+    pub fn cor_id(&self) -> CorId {
+        match self {
+            Self::Ok { cor_id, .. } | Self::Err { cor_id, .. } => *cor_id,
+        }
+    }
+
+    pub fn into_result(self) -> Result<DMsg, ServerError> {
+        match self {
+            Self::Ok { down_msg, .. } => Ok(down_msg),
+            Self::Err { error, .. } => Err(error),
+        }
+    }
 }
 
 #[cfg(feature = "serde")]
 #[derive(Deserialize)]
-pub struct DownMsgTransporterForDe<DMsg> {
-    pub down_msg: DMsg,
-    pub cor_id: CorId,
+pub enum DownMsgTransporterForDe<DMsg> {
+    Ok { down_msg: DMsg, cor_id: CorId },
+    Err { error: ServerError, cor_id: CorId },
 }
+
+#[cfg(feature = "serde")]
+impl<DMsg> DownMsgTransporterForDe<DMsg> {
+    pub fn cor_id(&self) -> CorId {
+        match self {
+            Self::Ok { cor_id, .. } | Self::Err { cor_id, .. } => *cor_id,
+        }
+    }
+
+    pub fn into_result(self) -> Result<DMsg, ServerError> {
+        match self {
+            Self::Ok { down_msg, .. } => Ok(down_msg),
+            Self::Err { error, .. } => Err(error),
+        }
+    }
+}
+
+// ------ ServerError ------
+
+/// Sent instead of a `DownMsg` when Moon failed to handle the `UpMsg`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServerError {
+    /// The `UpMsg` can't be deserialized or it contains invalid data.
+    Validation(ValidationErrors),
+    Unauthorized(String),
+    /// E.g. the `UpMsg` handler panicked.
+    Internal(String),
+    /// The `UpMsg` handler hasn't finished in time.
+    Timeout(String),
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Validation(errors) => write!(f, "validation failed: {errors}"),
+            Self::Unauthorized(message) => write!(f, "unauthorized: {message}"),
+            Self::Internal(message) => write!(f, "internal server error: {message}"),
+            Self::Timeout(message) => write!(f, "timeout: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
@@ -1,5 +1,11 @@
 use crate::*;
-use std::{fmt, str::FromStr};
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    str::FromStr,
+};
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -47,7 +53,157 @@ impl Deserialize for EntityId {
                 serde_lite::Error::invalid_value("EntityId can be deserialized only from String")
             })?
             .parse()
-            .map_err(|error| serde_lite::Error::invalid_value(error))
+            .map_err(serde_lite::Error::invalid_value)
             .map(Self)
     }
 }
+
+// ------ TypedEntityId ------
+
+/// Marks a [`TypedEntityId`]. Implement it with [`entity_ids!`](crate::entity_ids).
+pub trait EntityIdKind: 'static {
+    const NAME: &'static str;
+}
+
+/// `EntityId` that can't be mixed up with ids of other entities.
+/// Use [`entity_ids!`](crate::entity_ids) to create one.
+pub struct TypedEntityId<K: EntityIdKind>(Ulid, PhantomData<fn() -> K>);
+
+/// Creates [`TypedEntityId`] aliases together with their kinds, e.g. `ClientIdKind`.
+/// The kinds have the same visibility as the ids.
+///
+/// ```
+/// moonlight::entity_ids! {
+///     pub ClientId,
+///     pub ProjectId,
+/// }
+/// moonlight::entity_ids!(UserId);
+///
+/// let client_id = ClientId::new();
+/// assert_eq!(client_id, client_id.to_string().parse().unwrap());
+/// assert!(format!("{:?}", UserId::new()).starts_with("UserId("));
+/// ```
+#[macro_export]
+macro_rules! entity_ids {
+    ($($(#[$meta:meta])* $vis:vis $name:ident),* $(,)?) => {
+        $crate::paste::paste! {
+            $(
+                #[doc(hidden)]
+                #[derive(Debug)]
+                $vis enum [<$name Kind>] {}
+
+                impl $crate::EntityIdKind for [<$name Kind>] {
+                    const NAME: &'static str = stringify!($name);
+                }
+
+                $(#[$meta])*
+                $vis type $name = $crate::TypedEntityId<[<$name Kind>]>;
+            )*
+        }
+    };
+}
+
+impl<K: EntityIdKind> TypedEntityId<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: EntityIdKind> Default for TypedEntityId<K> {
+    fn default() -> Self {
+        Self(Ulid::generate(), PhantomData)
+    }
+}
+
+// Impls below are written by hand to not require any traits on `K`.
+
+impl<K: EntityIdKind> Clone for TypedEntityId<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: EntityIdKind> Copy for TypedEntityId<K> {}
+
+impl<K: EntityIdKind> PartialEq for TypedEntityId<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<K: EntityIdKind> Eq for TypedEntityId<K> {}
+
+impl<K: EntityIdKind> PartialOrd for TypedEntityId<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: EntityIdKind> Ord for TypedEntityId<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<K: EntityIdKind> Hash for TypedEntityId<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<K: EntityIdKind> fmt::Debug for TypedEntityId<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", K::NAME, self.0)
+    }
+}
+
+impl<K: EntityIdKind> fmt::Display for TypedEntityId<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<K: EntityIdKind> FromStr for TypedEntityId<K> {
+    type Err = DecodingError;
+
+    fn from_str(entity_id: &str) -> Result<Self, Self::Err> {
+        Ok(Self(entity_id.parse()?, PhantomData))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K: EntityIdKind> Serialize for TypedEntityId<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K: EntityIdKind> Deserialize<'de> for TypedEntityId<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ulid::deserialize(deserializer).map(|ulid| Self(ulid, PhantomData))
+    }
+}
+
+#[cfg(feature = "serde-lite")]
+impl<K: EntityIdKind> Serialize for TypedEntityId<K> {
+    fn serialize(&self) -> Result<serde_lite::Intermediate, serde_lite::Error> {
+        self.0.to_string().serialize()
+    }
+}
+
+#[cfg(feature = "serde-lite")]
+impl<K: EntityIdKind> Deserialize for TypedEntityId<K> {
+    fn deserialize(intermediate: &serde_lite::Intermediate) -> Result<Self, serde_lite::Error> {
+        intermediate
+            .as_str()
+            .ok_or_else(|| {
+                serde_lite::Error::invalid_value(format!(
+                    "{} can be deserialized only from String",
+                    K::NAME
+                ))
+            })?
+            .parse()
+            .map_err(serde_lite::Error::invalid_value)
+    }
+}
@@ -1,6 +1,9 @@
 pub use rusty_ulid::{self, DecodingError, Ulid};
 pub use serde_json;
 
+#[doc(hidden)]
+pub use paste;
+
 #[cfg(feature = "serde-lite")]
 pub use serde_lite::{self, Deserialize, Intermediate, Serialize};
 
@@ -21,13 +24,17 @@ mod cor_id;
 pub use cor_id::CorId;
 
 mod down_msg_transporter;
-pub use down_msg_transporter::{DownMsgTransporterForDe, DownMsgTransporterForSer};
+pub use down_msg_transporter::{DownMsgTransporterForDe, DownMsgTransporterForSer, ServerError};
 
 mod entity_id;
-pub use entity_id::EntityId;
+pub use entity_id::{EntityId, EntityIdKind, TypedEntityId};
 
 mod session_id;
 pub use session_id::SessionId;
 
+pub mod validate;
+pub use validate::{Validate, ValidationError, ValidationErrors};
+pub use validate_macro::Validate;
+
 mod wrapper;
 pub use wrapper::Wrapper;
@@ -2,6 +2,7 @@ use crate::*;
 use std::{fmt, str::FromStr};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SessionId(Ulid);
 
 impl SessionId {
@@ -23,3 +24,23 @@ impl FromStr for SessionId {
         Ok(SessionId(session_id.parse()?))
     }
 }
+
+#[cfg(feature = "serde-lite")]
+impl Serialize for SessionId {
+    fn serialize(&self) -> Result<Intermediate, serde_lite::Error> {
+        Ok(Intermediate::String(self.to_string()))
+    }
+}
+
+#[cfg(feature = "serde-lite")]
+impl Deserialize for SessionId {
+    fn deserialize(intermediate: &Intermediate) -> Result<Self, serde_lite::Error> {
+        intermediate
+            .as_str()
+            .ok_or_else(|| {
+                serde_lite::Error::invalid_value("SessionId can be deserialized only from String")
+            })?
+            .parse()
+            .map_err(|error| serde_lite::Error::invalid_value(error))
+    }
+}
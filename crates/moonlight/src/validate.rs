@@ -0,0 +1,311 @@
+//! Rules used by `#[derive(Validate)]`.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize, Validate)]
+//! pub enum UpMsg {
+//!     Login {
+//!         #[validate(length(min = 1, max = 50))]
+//!         name: String,
+//!         #[validate(length(min = 8))]
+//!         password: String,
+//!     },
+//!     SetAge(#[validate(range(min = 18, max = 150))] u8),
+//!     SetEmail(#[validate(email)] Option<String>),
+//!     SetPostcode(#[validate(regex = "^[0-9]{5}$")] String),
+//!     SetAddress(#[validate(nested)] Address),
+//! }
+//! ```
+//!
+//! The `regex` rule requires the `validate_regex` feature.
+
+use crate::*;
+use std::{borrow::Cow, fmt};
+
+// ------ Validate ------
+
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+// ------ ValidationErrors ------
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationErrors {
+    pub errors: Vec<ValidationError>,
+}
+
+/// `field` is a path like `address.postcode`, empty when the error isn't related to a field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(ValidationError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Adds errors of a nested value with field paths prefixed by `field`.
+    pub fn extend_nested(&mut self, field: &str, nested_errors: ValidationErrors) {
+        for ValidationError {
+            field: nested_field,
+            message,
+        } in nested_errors.errors
+        {
+            let field = if nested_field.is_empty() {
+                field.to_owned()
+            } else {
+                format!("{field}.{nested_field}")
+            };
+            self.push(field, message);
+        }
+    }
+
+    /// Messages for the given field, e.g. to display them next to a form input.
+    pub fn field_messages<'a>(&'a self, field: &'a str) -> impl Iterator<Item = &'a str> {
+        self.errors
+            .iter()
+            .filter(move |error| error.field == field)
+            .map(|error| error.message.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        Err(self)
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, ValidationError { field, message }) in self.errors.iter().enumerate() {
+            if index > 0 {
+                write!(f, "; ")?;
+            }
+            if field.is_empty() {
+                write!(f, "{message}")?;
+            } else {
+                write!(f, "{field}: {message}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+// ------ length ------
+
+pub trait Length {
+    /// Used in error messages, e.g. `character` or `item`.
+    const UNIT: &'static str;
+
+    fn length(&self) -> usize;
+}
+
+impl Length for str {
+    const UNIT: &'static str = "character";
+
+    fn length(&self) -> usize {
+        self.chars().count()
+    }
+}
+
+impl Length for String {
+    const UNIT: &'static str = "character";
+
+    fn length(&self) -> usize {
+        self.as_str().length()
+    }
+}
+
+impl Length for Cow<'_, str> {
+    const UNIT: &'static str = "character";
+
+    fn length(&self) -> usize {
+        self.as_ref().length()
+    }
+}
+
+impl<T> Length for [T] {
+    const UNIT: &'static str = "item";
+
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> Length for Vec<T> {
+    const UNIT: &'static str = "item";
+
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Strings are measured in chars, collections in items.
+pub fn length<T: Length + ?Sized>(
+    value: &T,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> Result<(), String> {
+    let length = value.length();
+    let units = |count: usize| {
+        let plural = if count == 1 { "" } else { "s" };
+        format!("{count} {}{plural}", T::UNIT)
+    };
+    match (min, max) {
+        (Some(min), _) if length < min => Err(format!("must have at least {}", units(min))),
+        (_, Some(max)) if length > max => Err(format!("must have at most {}", units(max))),
+        _ => Ok(()),
+    }
+}
+
+// ------ range ------
+
+pub fn range<T: PartialOrd + fmt::Display>(
+    value: &T,
+    min: Option<T>,
+    max: Option<T>,
+) -> Result<(), String> {
+    match (min, max) {
+        (Some(min), _) if *value < min => Err(format!("must be at least {min}")),
+        (_, Some(max)) if *value > max => Err(format!("must be at most {max}")),
+        _ => Ok(()),
+    }
+}
+
+// ------ email ------
+
+/// A basic check (`local@domain.tld` without whitespace), not a full RFC 5322 parser.
+pub fn email(value: &str) -> Result<(), String> {
+    let valid = match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain
+                    .split_once('.')
+                    .map(|(name, tld)| !name.is_empty() && !tld.is_empty() && !tld.ends_with('.'))
+                    .unwrap_or_default()
+                && !value.chars().any(char::is_whitespace)
+        }
+        None => false,
+    };
+    if valid {
+        return Ok(());
+    }
+    Err(String::from("must be a valid email address"))
+}
+
+// ------ regex ------
+
+/// Compiled on the first use.
+#[cfg(feature = "validate_regex")]
+pub struct LazyRegex {
+    pattern: &'static str,
+    regex: ::once_cell::sync::OnceCell<Result<::regex::Regex, ::regex::Error>>,
+}
+
+#[cfg(feature = "validate_regex")]
+impl LazyRegex {
+    pub const fn new(pattern: &'static str) -> Self {
+        Self {
+            pattern,
+            regex: ::once_cell::sync::OnceCell::new(),
+        }
+    }
+}
+
+#[cfg(feature = "validate_regex")]
+pub fn regex(value: &str, regex: &LazyRegex) -> Result<(), String> {
+    match regex
+        .regex
+        .get_or_init(|| ::regex::Regex::new(regex.pattern))
+    {
+        Ok(compiled_regex) if compiled_regex.is_match(value) => Ok(()),
+        Ok(_) => Err(format!("must match the pattern '{}'", regex.pattern)),
+        // `#[derive(Validate)]` rejects invalid patterns at compile time,
+        // so only a manually created `LazyRegex` can get here.
+        Err(error) => Err(format!(
+            "can't be validated, invalid pattern '{}': {error}",
+            regex.pattern
+        )),
+    }
+}
+
+// ====== ====== TESTS ====== ======
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        assert!(length("Martin", Some(1), Some(6)).is_ok());
+        assert!(length("Martin", Some(7), None).is_err());
+        assert_eq!(
+            length("Martin", Some(7), None),
+            Err(String::from("must have at least 7 characters"))
+        );
+        assert_eq!(
+            length(&vec![1, 2, 3], None, Some(1)),
+            Err(String::from("must have at most 1 item"))
+        );
+
+        assert!(range(&18, Some(18), Some(150)).is_ok());
+        assert!(range(&0.5, Some(1.), None).is_err());
+
+        assert!(email("martin@example.com").is_ok());
+        for invalid_email in [
+            "martin",
+            "@example.com",
+            "martin@example",
+            "mar tin@example.com",
+        ] {
+            assert!(email(invalid_email).is_err(), "{invalid_email}");
+        }
+    }
+
+    #[cfg(feature = "validate_regex")]
+    #[test]
+    fn test_invalid_regex() {
+        static REGEX: LazyRegex = LazyRegex::new("[0-9");
+        assert!(regex("12345", &REGEX)
+            .unwrap_err()
+            .starts_with("can't be validated, invalid pattern '[0-9'"));
+    }
+
+    #[test]
+    fn test_nested_errors() {
+        let mut address_errors = ValidationErrors::new();
+        address_errors.push("postcode", "must have at least 5 characters");
+        let mut errors = ValidationErrors::new();
+        errors.push("name", "must have at least 1 character");
+        errors.extend_nested("address", address_errors);
+
+        assert_eq!(
+            errors
+                .field_messages("address.postcode")
+                .collect::<Vec<_>>(),
+            ["must have at least 5 characters"]
+        );
+        assert_eq!(
+            errors.to_string(),
+            "name: must have at least 1 character; \
+             address.postcode: must have at least 5 characters"
+        );
+    }
+}
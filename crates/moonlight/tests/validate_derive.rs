@@ -0,0 +1,150 @@
+use moonlight::{Validate, ValidationError, ValidationErrors};
+
+#[derive(Validate)]
+struct Address {
+    #[validate(length(min = 5, max = 5))]
+    postcode: String,
+}
+
+#[derive(Validate)]
+struct Profile {
+    #[validate(length(min = 1, max = 50))]
+    name: String,
+    #[validate(email)]
+    email: Option<String>,
+    #[validate(range(min = 18, max = 150))]
+    age: u8,
+    #[validate(nested)]
+    address: Address,
+    #[validate(length(max = 2))]
+    tags: Vec<String>,
+}
+
+#[derive(Validate)]
+enum UpMsg {
+    Login {
+        #[validate(length(min = 8))]
+        password: String,
+    },
+    SetAge(#[validate(range(min = 18))] u8),
+    SetContact(
+        #[validate(email)] Option<String>,
+        #[validate(nested)] Option<Address>,
+    ),
+    Logout,
+}
+
+mod reexported {
+    pub use moonlight as lib;
+}
+
+#[derive(Validate)]
+#[validate(crate = "reexported::lib")]
+struct Score(#[validate(range(max = 10))] i32);
+
+#[cfg(feature = "validate_regex")]
+#[derive(Validate)]
+struct Postcode(#[validate(regex = "^[0-9]{5}$")] String);
+
+fn errors(result: Result<(), ValidationErrors>) -> Vec<(String, String)> {
+    result
+        .unwrap_err()
+        .errors
+        .into_iter()
+        .map(|ValidationError { field, message }| (field, message))
+        .collect()
+}
+
+fn address(postcode: &str) -> Address {
+    Address {
+        postcode: postcode.to_owned(),
+    }
+}
+
+#[test]
+fn test_struct() {
+    let profile = Profile {
+        name: String::from("Martin"),
+        email: None,
+        age: 18,
+        address: address("12345"),
+        tags: vec![String::from("rust")],
+    };
+    assert!(profile.validate().is_ok());
+
+    let profile = Profile {
+        name: String::new(),
+        email: Some(String::from("martin")),
+        age: 17,
+        address: address("123"),
+        tags: vec![
+            String::from("rust"),
+            String::from("wasm"),
+            String::from("web"),
+        ],
+    };
+    assert_eq!(
+        errors(profile.validate()),
+        [
+            ("name", "must have at least 1 character"),
+            ("email", "must be a valid email address"),
+            ("age", "must be at least 18"),
+            ("address.postcode", "must have at least 5 characters"),
+            ("tags", "must have at most 2 items"),
+        ]
+        .map(|(field, message)| (field.to_owned(), message.to_owned()))
+    );
+}
+
+#[test]
+fn test_enum() {
+    let password = String::from("short");
+    assert_eq!(
+        errors(UpMsg::Login { password }.validate()),
+        [(
+            String::from("password"),
+            String::from("must have at least 8 characters")
+        )]
+    );
+    assert_eq!(
+        errors(UpMsg::SetAge(17).validate()),
+        [(String::from("0"), String::from("must be at least 18"))]
+    );
+    assert_eq!(
+        errors(UpMsg::SetContact(Some(String::from("martin@")), Some(address("1"))).validate()),
+        [
+            (
+                String::from("0"),
+                String::from("must be a valid email address")
+            ),
+            (
+                String::from("1.postcode"),
+                String::from("must have at least 5 characters")
+            ),
+        ]
+    );
+    assert!(UpMsg::SetContact(None, None).validate().is_ok());
+    assert!(UpMsg::Logout.validate().is_ok());
+}
+
+#[test]
+fn test_crate_path() {
+    assert!(Score(10).validate().is_ok());
+    assert_eq!(
+        errors(Score(11).validate()),
+        [(String::from("0"), String::from("must be at most 10"))]
+    );
+}
+
+#[cfg(feature = "validate_regex")]
+#[test]
+fn test_regex() {
+    assert!(Postcode(String::from("12345")).validate().is_ok());
+    assert_eq!(
+        errors(Postcode(String::from("1234a")).validate()),
+        [(
+            String::from("0"),
+            String::from("must match the pattern '^[0-9]{5}$'")
+        )]
+    );
+}
@@ -37,7 +37,10 @@ pub async fn build_frontend(build_mode: BuildMode, cache_busting: bool, frontend
         write_build_id(build_id),
     )?;
     if build_mode.is_not_dev() && !frontend_dist {
-        compress_pkg(wasm_file_path.as_ref(), js_file_path.as_ref()).await?;
+        try_join!(
+            compress_pkg(wasm_file_path.as_ref(), js_file_path.as_ref()),
+            compress_public(),
+        )?;
     }
     println!("Frontend built");
 }
@@ -153,14 +156,44 @@ async fn compress_pkg(wasm_file_path: impl AsRef<Path>, js_file_path: impl AsRef
     )?
 }
 
+#[throws]
+async fn compress_public() {
+    // Compressed public files are saved to `frontend/pkg/public`
+    // to keep the `public` folder clean.
+    static PUBLIC_PATH: &str = "public";
+    static COMPRESSED_PUBLIC_PATH: &str = "frontend/pkg/public";
+
+    if fs::metadata(PUBLIC_PATH).await.is_err() {
+        return;
+    }
+    visit_files(PUBLIC_PATH)
+        .try_for_each_concurrent(None, |file| async move {
+            let file_path = file.path();
+            let output_path =
+                Path::new(COMPRESSED_PUBLIC_PATH).join(file_path.strip_prefix(PUBLIC_PATH)?);
+            if let Some(output_dir) = output_path.parent() {
+                fs::create_dir_all(output_dir)
+                    .await
+                    .with_context(|| format!("Failed to create the directory {:?}", output_dir))?;
+            }
+            create_compressed_files_with_output_path(&file_path, &output_path).await
+        })
+        .await?
+}
+
 #[throws]
 async fn create_compressed_files(file_path: impl AsRef<Path>) {
     let file_path = file_path.as_ref();
+    create_compressed_files_with_output_path(file_path, file_path).await?
+}
+
+#[throws]
+async fn create_compressed_files_with_output_path(file_path: &Path, output_path: &Path) {
     let content = Arc::new(fs::File::open(&file_path).await?.read_to_vec().await?);
 
     try_join!(
-        BrotliFileCompressor::compress_file(Arc::clone(&content), file_path, "br"),
-        GzipFileCompressor::compress_file(content, file_path, "gz"),
+        BrotliFileCompressor::compress_file(Arc::clone(&content), output_path, "br"),
+        GzipFileCompressor::compress_file(content, output_path, "gz"),
     )
     .with_context(|| format!("Failed to create compressed files for {:?}", file_path))?
 }
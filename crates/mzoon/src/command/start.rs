@@ -1,7 +1,7 @@
 use crate::build_backend::build_backend;
 use crate::build_frontend::build_frontend;
 use crate::config::Config;
-use crate::helper::localhost_url;
+use crate::helper::server_url;
 use crate::run_backend::run_backend;
 use crate::set_env_vars::set_env_vars;
 use crate::watcher::{BackendWatcher, FrontendWatcher};
@@ -18,12 +18,14 @@ const DEBOUNCE_TIME: Duration = Duration::from_millis(100);
 pub async fn start(build_mode: BuildMode, open: bool) {
     let config = Config::load_from_moonzoon_tomls().await?;
     set_env_vars(&config, build_mode, false);
+    let server_url = server_url(&config)?;
 
     let server = Arc::new(Mutex::new(None));
 
-    let frontend_watcher = build_and_watch_frontend(&config, build_mode).await?;
+    let frontend_watcher = build_and_watch_frontend(&config, &server_url, build_mode).await?;
     let backend_watcher =
-        build_run_and_watch_backend(&config, build_mode, open, Arc::clone(&server)).await?;
+        build_run_and_watch_backend(&config, &server_url, build_mode, open, Arc::clone(&server))
+            .await?;
 
     signal::ctrl_c().await?;
 
@@ -42,23 +44,28 @@ pub async fn start(build_mode: BuildMode, open: bool) {
 }
 
 #[throws]
-async fn build_and_watch_frontend(config: &Config, build_mode: BuildMode) -> FrontendWatcher {
+async fn build_and_watch_frontend(
+    config: &Config,
+    server_url: &str,
+    build_mode: BuildMode,
+) -> FrontendWatcher {
     if let Err(error) = build_frontend(build_mode, config.cache_busting, false).await {
         eprintln!("{error:#}");
     }
-    FrontendWatcher::start(&config, build_mode, DEBOUNCE_TIME).await?
+    FrontendWatcher::start(&config, server_url, build_mode, DEBOUNCE_TIME).await?
 }
 
 #[throws]
 async fn build_run_and_watch_backend(
     config: &Config,
+    server_url: &str,
     build_mode: BuildMode,
     open: bool,
     server: Arc<Mutex<Option<Child>>>,
 ) -> BackendWatcher {
     build_and_run_backend(config, build_mode, &server).await;
     if open {
-        open_in_browser(config)?;
+        open_in_browser(server_url)?;
     }
     BackendWatcher::start(&config, build_mode, DEBOUNCE_TIME, server).await?
 }
@@ -83,8 +90,7 @@ async fn build_and_run_backend(
 }
 
 #[throws]
-fn open_in_browser(config: &Config) {
-    let url = localhost_url(config);
+fn open_in_browser(url: &str) {
     println!("Open {url} in the default web browser");
     open::that(url).context("Failed to open the URL in the browser")?;
 }
@@ -6,16 +6,29 @@ use anyhow::{Context, Error};
 use fehler::throws;
 use log::LevelFilter;
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use tokio::fs;
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub port: u16,
+    #[serde(default)]
+    pub bind_address: Vec<String>,
+    #[serde(default)]
+    pub unix_socket: Option<String>,
     pub https: bool,
     pub cache_busting: bool,
+    #[serde(default)]
+    pub fingerprinted_public: bool,
     pub backend_log_level: LevelFilter,
+    #[serde(default)]
+    pub up_msg_handler_timeout: Option<u64>,
     pub redirect: Redirect,
     pub cors: Cors,
+    #[serde(default)]
+    pub tls: Tls,
+    #[serde(default)]
+    pub sse: Sse,
     pub watch: Watch,
     #[serde(skip)]
     pub custom_env_vars: Vec<(String, String)>,
@@ -25,11 +38,57 @@ pub struct Config {
 pub struct Redirect {
     pub port: u16,
     pub enabled: bool,
+    #[serde(default)]
+    pub canonical_host: Option<String>,
+    #[serde(default)]
+    pub trailing_slash: Option<String>,
+    #[serde(default)]
+    pub permanent: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Cors {
     pub origins: Vec<String>,
+    #[serde(default)]
+    pub credentials: bool,
+    #[serde(default)]
+    pub allowed_headers: Option<Vec<String>>,
+    #[serde(default)]
+    pub exposed_headers: Vec<String>,
+    #[serde(default)]
+    pub max_age: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Tls {
+    pub reload: bool,
+    pub sni_hosts: Vec<String>,
+}
+
+impl Default for Tls {
+    fn default() -> Self {
+        Self {
+            reload: true,
+            sni_hosts: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Sse {
+    pub queue_capacity: usize,
+    pub overflow_policy: String,
+}
+
+impl Default for Sse {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 1000,
+            overflow_policy: "drop_oldest".to_owned(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,5 +1,5 @@
 use crate::config::Config;
-use crate::helper::{download, localhost_url};
+use crate::helper::{download, server_url};
 use crate::run_backend::run_backend;
 use crate::{BuildMode, Hosting};
 use anyhow::Error;
@@ -43,8 +43,9 @@ async fn recreate_api_dir_with_frontend_dist() {
 
 #[throws]
 async fn recreate_index_html(build_mode: BuildMode, config: &Config) {
+    let url = server_url(config)?;
     let server = run_backend(build_mode)?;
-    let html = download(localhost_url(config)).await?;
+    let html = download(url).await?;
     drop(server);
 
     fs::write(concatcp!(FRONTEND_DIST_DIR, "/index.html"), html).await?;
@@ -1,14 +1,14 @@
 mod download;
 mod file_compressor;
-mod localhost_url;
 mod read_to_vec;
+mod server_url;
 pub mod tree_into_pairs;
 mod try_into_string;
 mod visit_files;
 
 pub use download::download;
 pub use file_compressor::{BrotliFileCompressor, FileCompressor, GzipFileCompressor};
-pub use localhost_url::localhost_url;
 pub use read_to_vec::{AsyncReadToVec, ReadToVec};
+pub use server_url::server_url;
 pub use try_into_string::TryIntoString;
 pub use visit_files::visit_files;
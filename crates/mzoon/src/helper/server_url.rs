@@ -0,0 +1,36 @@
+use crate::config::Config;
+use anyhow::{bail, Context, Error};
+use fehler::throws;
+use std::net::IpAddr;
+
+/// The URL of the Moon server started by mzoon, based on `bind_address` and `port`.
+#[throws]
+pub fn server_url(config: &Config) -> String {
+    if let Some(unix_socket) = &config.unix_socket {
+        // mzoon's HTTP client can't connect through a Unix socket.
+        bail!(
+            "unix_socket = \"{unix_socket}\" in MoonZoon.toml isn't supported by mzoon commands \
+            that run the Moon server, remove it or move it to the production config"
+        );
+    }
+    let host = match config.bind_address.first() {
+        Some(address) => {
+            let ip = address
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+                .with_context(|| format!("Invalid bind_address '{address}' in MoonZoon.toml"))?;
+            match ip {
+                ip if ip.is_unspecified() => "localhost".to_owned(),
+                IpAddr::V4(ip) => ip.to_string(),
+                IpAddr::V6(ip) => format!("[{ip}]"),
+            }
+        }
+        None => "localhost".to_owned(),
+    };
+    format!(
+        "{protocol}://{host}:{port}",
+        protocol = if config.https { "https" } else { "http" },
+        port = config.port
+    )
+}
@@ -4,28 +4,95 @@ use std::env;
 pub fn set_env_vars(config: &Config, build_mode: BuildMode, frontend_dist: bool) {
     // port = 8443
     env::set_var("PORT", config.port.to_string());
+    // bind_address = ["127.0.0.1", "[::1]"]
+    if !config.bind_address.is_empty() {
+        env::set_var("BIND_ADDRESS", config.bind_address.join(","));
+    }
+    // unix_socket = "/run/moon/app.sock"
+    if let Some(unix_socket) = &config.unix_socket {
+        env::set_var("UNIX_SOCKET", unix_socket);
+    }
     // https = true
     env::set_var("HTTPS", config.https.to_string());
     // cache_busting = true
     env::set_var("CACHE_BUSTING", config.cache_busting.to_string());
+    // fingerprinted_public = false
+    env::set_var(
+        "FINGERPRINTED_PUBLIC",
+        config.fingerprinted_public.to_string(),
+    );
     // backend_log_level = "warn"
     env::set_var("BACKEND_LOG_LEVEL", config.backend_log_level.as_str());
+    // up_msg_handler_timeout = 60
+    if let Some(timeout) = config.up_msg_handler_timeout {
+        env::set_var("UP_MSG_HANDLER_TIMEOUT", timeout.to_string());
+    }
 
     // [redirect]
     // port = 8080
     env::set_var("REDIRECT_PORT", config.redirect.port.to_string());
     // enabled = true
     env::set_var("REDIRECT_ENABLED", config.redirect.enabled.to_string());
+    // canonical_host = "example.com"
+    if let Some(canonical_host) = &config.redirect.canonical_host {
+        env::set_var("REDIRECT_CANONICAL_HOST", canonical_host);
+    }
+    // trailing_slash = "remove"
+    if let Some(trailing_slash) = &config.redirect.trailing_slash {
+        env::set_var("REDIRECT_TRAILING_SLASH", trailing_slash);
+    }
+    // [redirect.permanent]
+    // "/old-page" = "/new-page"
+    // Passed as a TOML table because paths and URLs may contain commas.
+    let permanent_redirects = toml::to_string(&config.redirect.permanent)
+        .expect("failed to serialize permanent redirects");
+    env::set_var("REDIRECT_PERMANENT", permanent_redirects);
 
     // [cors]
     // origins = ["*", "https://example.com"]
     env::set_var("CORS_ORIGINS", config.cors.origins.join(","));
+    // credentials = false
+    env::set_var("CORS_CREDENTIALS", config.cors.credentials.to_string());
+    // allowed_headers = ["Content-Type", "X-Session-ID", "X-Correlation-ID", "X-Auth-Token"]
+    if let Some(allowed_headers) = &config.cors.allowed_headers {
+        env::set_var("CORS_ALLOWED_HEADERS", allowed_headers.join(","));
+    }
+    // exposed_headers = ["X-Request-ID"]
+    if !config.cors.exposed_headers.is_empty() {
+        env::set_var(
+            "CORS_EXPOSED_HEADERS",
+            config.cors.exposed_headers.join(","),
+        );
+    }
+    // max_age = 3600
+    if let Some(max_age) = config.cors.max_age {
+        env::set_var("CORS_MAX_AGE", max_age.to_string());
+    }
+
+    // [tls]
+    // reload = true
+    env::set_var("TLS_RELOAD", config.tls.reload.to_string());
+    // sni_hosts = ["example.com", "www.example.com"]
+    if !config.tls.sni_hosts.is_empty() {
+        env::set_var("TLS_SNI_HOSTS", config.tls.sni_hosts.join(","));
+    }
+
+    // [sse]
+    // queue_capacity = 1000
+    env::set_var("SSE_QUEUE_CAPACITY", config.sse.queue_capacity.to_string());
+    // overflow_policy = "drop_oldest"
+    env::set_var("SSE_OVERFLOW_POLICY", &config.sse.overflow_policy);
 
     env::set_var(
         "COMPRESSED_PKG",
         (build_mode.is_not_dev() && !frontend_dist).to_string(),
     );
 
+    env::set_var(
+        "COMPRESSED_PUBLIC",
+        (build_mode.is_not_dev() && !frontend_dist).to_string(),
+    );
+
     env::set_var("FRONTEND_DIST", frontend_dist.to_string());
 
     for (key, value) in &config.custom_env_vars {
@@ -15,16 +15,17 @@ pub struct FrontendWatcher {
 
 impl FrontendWatcher {
     #[throws]
-    pub async fn start(config: &Config, build_mode: BuildMode, debounce_time: Duration) -> Self {
+    pub async fn start(
+        config: &Config,
+        server_url: &str,
+        build_mode: BuildMode,
+        debounce_time: Duration,
+    ) -> Self {
         let (watcher, debounced_receiver) =
             ProjectWatcher::start(&config.watch.frontend, debounce_time)
                 .context("Failed to start the frontend project watcher")?;
 
-        let reload_url = Arc::new(format!(
-            "{protocol}://localhost:{port}/_api/reload",
-            protocol = if config.https { "https" } else { "http" },
-            port = config.port
-        ));
+        let reload_url = Arc::new(format!("{server_url}/_api/reload"));
 
         Self {
             watcher,
@@ -0,0 +1,264 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, punctuated::Punctuated, spanned::Spanned, Attribute, Data, DeriveInput,
+    Error, Fields, Lit, Meta, NestedMeta, Path, Result, Token, Type,
+};
+
+// ```
+// #[derive(Validate)]
+// pub enum UpMsg {
+//     Login {
+//         #[validate(length(min = 1, max = 50))]
+//         name: String,
+//         #[validate(length(min = 8))]
+//         password: String,
+//     },
+//     Logout,
+// }
+// ```
+//
+// generates:
+//
+// ```
+// impl moonlight::Validate for UpMsg {
+//     fn validate(&self) -> Result<(), moonlight::ValidationErrors> {
+//         let mut errors = moonlight::ValidationErrors::new();
+//         match self {
+//             Self::Login { name, password, .. } => {
+//                 let value = name;
+//                 if let Err(message) = moonlight::validate::length(value, Some(1), Some(50)) {
+//                     errors.push("name", message);
+//                 }
+//                 let value = password;
+//                 if let Err(message) = moonlight::validate::length(value, Some(8), None) {
+//                     errors.push("password", message);
+//                 }
+//             }
+//             Self::Logout { .. } => {}
+//         }
+//         errors.into_result()
+//     }
+// }
+// ```
+//
+// Rules of `Option` fields are checked only when the value is `Some`.
+// Use `#[validate(crate = "zoon::moonlight")]` on the type when `moonlight`
+// isn't a direct dependency.
+
+#[proc_macro_derive(Validate, attributes(validate))]
+pub fn derive_validate(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let krate = crate_path(&input.attrs)?;
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, checks) = fields_pattern_and_checks(&data.fields, &krate)?;
+            quote! {
+                let Self #pattern = self;
+                #checks
+            }
+        }
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let (pattern, checks) = fields_pattern_and_checks(&variant.fields, &krate)?;
+                arms.push(quote! { Self::#ident #pattern => { #checks } });
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(data) => Err(Error::new(
+            data.union_token.span(),
+            "Validate can't be derived for unions",
+        ))?,
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::Validate for #ident #ty_generics #where_clause {
+            #[allow(unused_variables, unused_mut)]
+            fn validate(&self) -> ::core::result::Result<(), #krate::ValidationErrors> {
+                let mut errors = #krate::ValidationErrors::new();
+                #body
+                errors.into_result()
+            }
+        }
+    })
+}
+
+fn crate_path(attrs: &[Attribute]) -> Result<Path> {
+    for meta in validate_metas(attrs)? {
+        match meta {
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("crate") => {
+                return match &name_value.lit {
+                    Lit::Str(path) => path.parse(),
+                    lit => Err(Error::new_spanned(lit, "string with a crate path expected")),
+                };
+            }
+            meta => Err(Error::new_spanned(
+                meta,
+                "only `crate = \"...\"` expected here",
+            ))?,
+        }
+    }
+    Ok(syn::parse_quote!(moonlight))
+}
+
+fn validate_metas(attrs: &[Attribute]) -> Result<Vec<NestedMeta>> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("validate")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => metas.extend(list.nested),
+            meta => Err(Error::new_spanned(meta, "expected `#[validate(...)]`"))?,
+        }
+    }
+    Ok(metas)
+}
+
+// Returns e.g. `{ name, password, .. }` and checks of the bound fields.
+fn fields_pattern_and_checks(
+    fields: &Fields,
+    krate: &Path,
+) -> Result<(TokenStream2, TokenStream2)> {
+    let mut bindings = Vec::new();
+    let mut checks = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let rules = validate_metas(&field.attrs)?;
+        if rules.is_empty() {
+            continue;
+        }
+        let (binding, name) = match &field.ident {
+            Some(ident) => (
+                ident.clone(),
+                ident.to_string().trim_start_matches("r#").to_owned(),
+            ),
+            None => (format_ident!("field_{}", index), index.to_string()),
+        };
+        let member = match &field.ident {
+            Some(ident) => quote! { #ident },
+            None => {
+                let index = syn::Index::from(index);
+                quote! { #index }
+            }
+        };
+        let rule_checks = rules
+            .iter()
+            .map(|rule| rule_check(rule, &name, krate))
+            .collect::<Result<Vec<_>>>()?;
+        let rule_checks = if is_option(&field.ty) {
+            quote! {
+                if let ::core::option::Option::Some(value) = value {
+                    #(#rule_checks)*
+                }
+            }
+        } else {
+            quote! { #(#rule_checks)* }
+        };
+        checks.push(quote! {
+            let value = #binding;
+            #rule_checks
+        });
+        bindings.push(quote! { #member: #binding });
+    }
+    // `{ .. }` is a valid pattern also for tuple and unit structs and variants.
+    let pattern = quote! { { #(#bindings,)* .. } };
+    Ok((pattern, quote! { #(#checks)* }))
+}
+
+fn rule_check(rule: &NestedMeta, name: &str, krate: &Path) -> Result<TokenStream2> {
+    let push_error = quote! {
+        if let ::core::result::Result::Err(message) = check {
+            errors.push(#name, message);
+        }
+    };
+    let check = match rule {
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("email") => {
+            quote! { #krate::validate::email(value) }
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("nested") => {
+            return Ok(quote! {
+                if let ::core::result::Result::Err(nested_errors) = #krate::Validate::validate(value) {
+                    errors.extend_nested(#name, nested_errors);
+                }
+            });
+        }
+        NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("length") => {
+            let [min, max] = min_max(&list.nested)?;
+            quote! { #krate::validate::length(value, #min, #max) }
+        }
+        NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("range") => {
+            let [min, max] = min_max(&list.nested)?;
+            quote! { #krate::validate::range(value, #min, #max) }
+        }
+        NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("regex") => {
+            match &name_value.lit {
+                Lit::Str(pattern) => {
+                    #[cfg(feature = "regex")]
+                    if let Err(error) = regex::Regex::new(&pattern.value()) {
+                        let message = format!("invalid regular expression: {error}");
+                        Err(Error::new_spanned(pattern, message))?
+                    }
+                    quote! {{
+                        static REGEX: #krate::validate::LazyRegex =
+                            #krate::validate::LazyRegex::new(#pattern);
+                        #krate::validate::regex(value, &REGEX)
+                    }}
+                }
+                lit => Err(Error::new_spanned(lit, "string with a regular expression expected"))?,
+            }
+        }
+        rule => Err(Error::new_spanned(
+            rule,
+            "unknown rule, expected `length(..)`, `range(..)`, `email`, `regex = \"..\"` or `nested`",
+        ))?,
+    };
+    Ok(quote! {
+        let check = #check;
+        #push_error
+    })
+}
+
+// Returns `[Some(min) or None, Some(max) or None]`.
+fn min_max(args: &Punctuated<NestedMeta, Token![,]>) -> Result<[TokenStream2; 2]> {
+    let mut min = quote! { ::core::option::Option::None };
+    let mut max = quote! { ::core::option::Option::None };
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("min") => {
+                let lit = &name_value.lit;
+                min = quote! { ::core::option::Option::Some(#lit) };
+            }
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("max") => {
+                let lit = &name_value.lit;
+                max = quote! { ::core::option::Option::Some(#lit) };
+            }
+            arg => Err(Error::new_spanned(arg, "expected `min = ..` or `max = ..`"))?,
+        }
+    }
+    Ok([min, max])
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Option")
+            .unwrap_or_default(),
+        _ => false,
+    }
+}
@@ -1,7 +1,7 @@
 use crate::*;
-use futures_channel::oneshot;
+use futures_channel::{mpsc, oneshot};
 use moonlight::serde::{de::DeserializeOwned, Serialize};
-use moonlight::{serde_json, AuthToken, CorId, SessionId};
+use moonlight::{serde_json, AuthToken, CorId, ServerError, SessionId};
 use std::{
     collections::BTreeMap,
     error::Error,
@@ -10,59 +10,196 @@ use std::{
     pin::Pin,
     sync::{Arc, Mutex},
 };
-use web_sys::{Request, RequestInit, Response};
+use web_sys::{Request, RequestCredentials, RequestInit, Response};
+
+mod mock;
+pub use mock::{MockConnection, MockResponse};
+
+mod offline_queue;
+use offline_queue::OfflineQueue;
+
+mod retry;
+pub use retry::RetryPolicy;
 
 mod sse;
 use sse::SSE;
 
+mod status;
+pub use status::ConnectionStatus;
+use status::{InFlightGuard, OnlineStatus};
+
+mod upload;
+pub use upload::{Upload, UploadError, UploadProgress};
+
 // ------ DMsgSenders ------
 
-struct DMsgSenders<DMsg>(Arc<Mutex<BTreeMap<CorId, oneshot::Sender<DMsg>>>>);
+struct DMsgSenders<Sender>(Arc<Mutex<BTreeMap<CorId, Sender>>>);
 
-impl<DMsg> DMsgSenders<DMsg> {
+impl<Sender> DMsgSenders<Sender> {
     fn new() -> Self {
         Self(Arc::new(Mutex::new(BTreeMap::new())))
     }
 
-    fn remove(&self, cor_id: &CorId) -> Option<oneshot::Sender<DMsg>> {
+    fn remove(&self, cor_id: &CorId) -> Option<Sender> {
         self.0.lock().unwrap_throw().remove(cor_id)
     }
 
-    fn insert(&self, cor_id: CorId, sender: oneshot::Sender<DMsg>) {
+    fn insert(&self, cor_id: CorId, sender: Sender) {
         self.0.lock().unwrap_throw().insert(cor_id, sender);
     }
+
+    fn get(&self, cor_id: &CorId) -> Option<Sender>
+    where
+        Sender: Clone,
+    {
+        self.0.lock().unwrap_throw().get(cor_id).cloned()
+    }
 }
 
-impl<DMsg> Clone for DMsgSenders<DMsg> {
+impl<Sender> Clone for DMsgSenders<Sender> {
     fn clone(&self) -> Self {
         DMsgSenders(Arc::clone(&self.0))
     }
 }
 
+// ------ PendingDownMsg ------
+
+// Removes the `DownMsg` sender when `exchange_msgs` has failed, timed out
+// or its future has been dropped. The sender is already removed when the `DownMsg` has arrived.
+struct PendingDownMsg<DMsg> {
+    cor_id: CorId,
+    d_msg_senders: DMsgSenders<oneshot::Sender<Result<DMsg, ServerError>>>,
+    cancel_request_context: Option<RequestContext>,
+}
+
+impl<DMsg> Drop for PendingDownMsg<DMsg> {
+    fn drop(&mut self) {
+        if self.d_msg_senders.remove(&self.cor_id).is_none() {
+            return;
+        }
+        if let Some(request_context) = self.cancel_request_context.take() {
+            let cor_id = self.cor_id;
+            Task::start(async move {
+                if let Err(error) = fetch_cancel(&request_context, cor_id).await {
+                    crate::eprintln!("Cancelling UpMsg '{}' failed: {}", cor_id, error);
+                }
+            });
+        }
+    }
+}
+
+// ------ FRONTEND_VERSION ------
+
+// Moon adds the meta tag with the version the frontend has been built with.
+static FRONTEND_VERSION: once_cell::sync::Lazy<Option<String>> = once_cell::sync::Lazy::new(|| {
+    document()
+        .query_selector(r#"meta[name="moonzoon-version"]"#)
+        .ok()
+        .flatten()
+        .and_then(|meta| meta.get_attribute("content"))
+});
+
+fn check_version(version_mismatch: &Mutable<bool>, backend_version: &str) {
+    if let Some(frontend_version) = FRONTEND_VERSION.as_deref() {
+        if frontend_version != backend_version {
+            version_mismatch.set_neq(true);
+        }
+    }
+}
+
+// ------ persisted_session_id ------
+
+const SESSION_ID_STORAGE_KEY: &str = "moonzoon_session_id";
+const SESSION_ID_IN_USE_STORAGE_KEY: &str = "moonzoon_session_id_in_use";
+
+// The session id survives page reloads so Moon can resume the session
+// instead of creating a new one. `sessionStorage` is separate for each tab.
+#[cfg(feature = "web_storage")]
+fn persisted_session_id() -> SessionId {
+    let storage = match SessionStorage::try_new() {
+        Ok(storage) => storage,
+        Err(_) => return SessionId::new(),
+    };
+    // A duplicated tab gets a copy of `sessionStorage` including the in-use flag
+    // set by the original tab. The flag is removed on `pagehide`, so a reloaded tab doesn't have it.
+    let in_use = matches!(storage.get(SESSION_ID_IN_USE_STORAGE_KEY), Some(Ok(true)));
+    let session_id = match storage.get(SESSION_ID_STORAGE_KEY) {
+        Some(Ok(session_id)) if !in_use => session_id,
+        _ => {
+            let session_id = SessionId::new();
+            // Storage may be unavailable, e.g. in private mode. We just won't resume the session then.
+            let _ = storage.insert(SESSION_ID_STORAGE_KEY, &session_id);
+            session_id
+        }
+    };
+    mark_session_id_in_use();
+    session_id
+}
+
+#[cfg(feature = "web_storage")]
+fn mark_session_id_in_use() {
+    let set_in_use = |in_use: bool| {
+        if let Ok(storage) = SessionStorage::try_new() {
+            if in_use {
+                let _ = storage.insert(SESSION_ID_IN_USE_STORAGE_KEY, &true);
+            } else {
+                storage.remove(SESSION_ID_IN_USE_STORAGE_KEY);
+            }
+        }
+    };
+    set_in_use(true);
+
+    // `pageshow` is fired also when the page is restored from the back-forward cache.
+    let window = window();
+    for (event, in_use) in [("pagehide", false), ("pageshow", true)] {
+        let listener = Closure::<dyn Fn()>::new(move || set_in_use(in_use));
+        window
+            .add_event_listener_with_callback(event, listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+        // The listeners live as long as the page.
+        listener.forget();
+    }
+}
+
+#[cfg(not(feature = "web_storage"))]
+fn persisted_session_id() -> SessionId {
+    SessionId::new()
+}
+
 // ------ Connection ------
 
 pub struct Connection<UMsg, DMsg> {
     session_id: SessionId,
-    _sse: SSE,
+    sse: SSE,
+    api_endpoint: Arc<Mutex<ApiEndpoint>>,
     auth_token_getter:
         Option<Box<dyn Fn() -> Pin<Box<dyn Future<Output = Option<AuthToken>>>> + Send + Sync>>,
     msg_types: PhantomData<(UMsg, DMsg)>,
-    d_msg_senders: DMsgSenders<DMsg>,
+    d_msg_senders: DMsgSenders<oneshot::Sender<Result<DMsg, ServerError>>>,
+    d_msg_stream_senders: DMsgSenders<mpsc::UnboundedSender<DMsg>>,
+    version_mismatch: Mutable<bool>,
+    retry_policy: RetryPolicy,
+    offline_queue: Option<OfflineQueue>,
+    status: Mutable<ConnectionStatus>,
+    online_status: OnlineStatus,
+    in_flight_count: Mutable<usize>,
 }
 
 impl<UMsg: Serialize, DMsg: DeserializeOwned + 'static> Connection<UMsg, DMsg> {
     pub fn new(down_msg_handler: impl FnMut(DMsg, CorId) + Send + Sync + 'static) -> Self {
         let d_msg_senders = DMsgSenders::new();
+        let d_msg_stream_senders = DMsgSenders::<mpsc::UnboundedSender<DMsg>>::new();
+        let shared_down_msg_handler = Arc::new(Mutex::new(down_msg_handler));
 
         let down_msg_handler = {
             let d_msg_senders = d_msg_senders.clone();
-            let down_msg_handler = Arc::new(Mutex::new(down_msg_handler));
+            let down_msg_handler = Arc::clone(&shared_down_msg_handler);
 
             move |d_msg: DMsg, cor_id: CorId| {
                 if let Some(d_msg_sender) = d_msg_senders.remove(&cor_id) {
                     let down_msg_handler = Arc::clone(&down_msg_handler);
                     Task::start(async move {
-                        if let Err(d_msg) = d_msg_sender.send(d_msg) {
+                        if let Err(Ok(d_msg)) = d_msg_sender.send(Ok(d_msg)) {
                             (down_msg_handler.lock().unwrap_throw())(d_msg, cor_id);
                         }
                     });
@@ -72,13 +209,81 @@ impl<UMsg: Serialize, DMsg: DeserializeOwned + 'static> Connection<UMsg, DMsg> {
             }
         };
 
-        let session_id = SessionId::new();
+        let down_msg_stream_handler = {
+            let d_msg_stream_senders = d_msg_stream_senders.clone();
+            let down_msg_handler = Arc::clone(&shared_down_msg_handler);
+
+            move |d_msg: DMsg, cor_id: CorId| {
+                let d_msg = match d_msg_stream_senders.get(&cor_id) {
+                    Some(d_msg_sender) => match d_msg_sender.unbounded_send(d_msg) {
+                        Ok(()) => return,
+                        Err(error) => {
+                            // The stream has been dropped.
+                            d_msg_stream_senders.remove(&cor_id);
+                            error.into_inner()
+                        }
+                    },
+                    None => d_msg,
+                };
+                (down_msg_handler.lock().unwrap_throw())(d_msg, cor_id)
+            }
+        };
+
+        let down_msg_stream_end_handler = {
+            let d_msg_stream_senders = d_msg_stream_senders.clone();
+            move |cor_id: CorId| {
+                d_msg_stream_senders.remove(&cor_id);
+            }
+        };
+
+        let server_error_handler = {
+            let d_msg_senders = d_msg_senders.clone();
+            let d_msg_stream_senders = d_msg_stream_senders.clone();
+            move |error: ServerError, cor_id: CorId| {
+                if let Some(d_msg_sender) = d_msg_senders.remove(&cor_id) {
+                    let _ = d_msg_sender.send(Err(error));
+                    return;
+                }
+                // Removing the sender ends the stream returned from `exchange_msgs_stream`.
+                if d_msg_stream_senders.remove(&cor_id).is_none() {
+                    crate::eprintln!("UpMsg '{}' failed: {}", cor_id, error);
+                }
+            }
+        };
+
+        let version_mismatch = Mutable::new(false);
+        let version_handler = {
+            let version_mismatch = version_mismatch.clone();
+            move |backend_version: String| check_version(&version_mismatch, &backend_version)
+        };
+
+        let status = Mutable::new(ConnectionStatus::Connecting);
+
+        let session_id = persisted_session_id();
+        let api_endpoint = ApiEndpoint::default();
+        let sse_url = api_endpoint.url(&format!("message_sse/{}", session_id));
         Self {
             session_id,
-            _sse: SSE::new(session_id, down_msg_handler),
+            sse: SSE::new(
+                &sse_url,
+                down_msg_handler,
+                down_msg_stream_handler,
+                down_msg_stream_end_handler,
+                server_error_handler,
+                version_handler,
+                status.clone(),
+            ),
+            api_endpoint: Arc::new(Mutex::new(api_endpoint)),
             auth_token_getter: None,
             msg_types: PhantomData,
             d_msg_senders,
+            d_msg_stream_senders,
+            version_mismatch,
+            retry_policy: RetryPolicy::default(),
+            offline_queue: None,
+            status,
+            online_status: OnlineStatus::new(),
+            in_flight_count: Mutable::new(0),
         }
     }
 
@@ -98,6 +303,118 @@ impl<UMsg: Serialize, DMsg: DeserializeOwned + 'static> Connection<UMsg, DMsg> {
         self
     }
 
+    /// The default policy for all `UpMsg`s, see `MsgOptions::retry_policy`.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// `UpMsg`s sent while the browser is offline or Moon is unreachable
+    /// are stored in `local_storage()` (with the `web_storage` feature) and sent
+    /// in the original order once the connectivity returns.
+    /// Each tab has its own queue keyed by its persisted session id, so the `UpMsg`s are sent only once.
+    /// `send_up_msg` returns `Ok` for queued `UpMsg`s.
+    ///
+    /// _Note:_ Auth tokens of queued `UpMsg`s are stored together with them.
+    pub fn offline_queue(mut self, enabled: bool) -> Self {
+        self.offline_queue = enabled.then(|| OfflineQueue::new(self.request_context()));
+        self
+    }
+
+    /// The origin of the Moon backend, e.g. `https://api.example.com`,
+    /// when it's different from the frontend's origin.
+    /// Don't forget to allow the frontend's origin in Moon's CORS config.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into();
+        self.api_endpoint.lock().unwrap_throw().base_url =
+            base_url.trim_end_matches('/').to_owned();
+        self.reconnect_sse();
+        self
+    }
+
+    /// Sent with all requests to Moon except the `EventSource` connection
+    /// because browsers don't allow custom headers for it.
+    pub fn header(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.api_endpoint
+            .lock()
+            .unwrap_throw()
+            .headers
+            .push((name.into(), value.into()));
+        self
+    }
+
+    /// Called before each request to Moon, see `header`.
+    pub fn headers_getter<FH>(self, getter: impl Fn() -> FH + Send + Sync + 'static) -> Self
+    where
+        FH: Future<Output = Vec<(String, String)>> + 'static,
+    {
+        let getter = Arc::new(getter);
+        self.api_endpoint.lock().unwrap_throw().headers_getter = Some(Arc::new(move || {
+            let getter = Arc::clone(&getter);
+            Box::pin(async move { getter().await })
+        }));
+        self
+    }
+
+    /// Sends cookies also to the backend on another origin (`credentials: "include"`)
+    /// and enables `withCredentials` for the `EventSource`.
+    /// Moon has to allow credentials in its CORS config.
+    pub fn with_credentials(mut self, with_credentials: bool) -> Self {
+        self.api_endpoint.lock().unwrap_throw().with_credentials = with_credentials;
+        self.reconnect_sse();
+        self
+    }
+
+    // Only updates the URL when the `EventSource` hasn't been opened yet, see `SSE`.
+    fn reconnect_sse(&mut self) {
+        let (url, with_credentials) = {
+            let api_endpoint = self.api_endpoint.lock().unwrap_throw();
+            let url = api_endpoint.url(&format!("message_sse/{}", self.session_id));
+            (url, api_endpoint.with_credentials)
+        };
+        self.sse.reconnect(&url, with_credentials);
+    }
+
+    fn request_context(&self) -> RequestContext {
+        RequestContext {
+            session_id: self.session_id,
+            version_mismatch: self.version_mismatch.clone(),
+            api_endpoint: Arc::clone(&self.api_endpoint),
+        }
+    }
+
+    /// Fires `true` when Moon has been redeployed with a different frontend version
+    /// than the one running in this browser tab. The app should ask the user to reload the page.
+    pub fn version_mismatch_signal(&self) -> impl Signal<Item = bool> {
+        self.version_mismatch.signal()
+    }
+
+    /// `Open` is reported as `Reconnecting` while the browser is offline.
+    ///
+    /// ```ignore
+    /// connection().status_signal().map(|status| status != ConnectionStatus::Open)
+    /// ```
+    pub fn status_signal(&self) -> impl Signal<Item = ConnectionStatus> {
+        map_ref! {
+            let status = self.status.signal(),
+            let online = self.online_status.signal() =>
+            match (*status, *online) {
+                (ConnectionStatus::Open, false) => ConnectionStatus::Reconnecting,
+                (status, _) => status,
+            }
+        }
+    }
+
+    /// Follows `navigator.onLine`.
+    pub fn online_signal(&self) -> impl Signal<Item = bool> {
+        self.online_status.signal()
+    }
+
+    /// The number of `exchange_msgs` calls waiting for their `DownMsg`.
+    pub fn in_flight_count_signal(&self) -> impl Signal<Item = usize> {
+        self.in_flight_count.signal()
+    }
+
     pub async fn send_up_msg(&self, up_msg: UMsg) -> Result<CorId, SendUpMsgError> {
         self.send_up_msg_with_options(up_msg, MsgOptions::default())
             .await
@@ -118,51 +435,77 @@ impl<UMsg: Serialize, DMsg: DeserializeOwned + 'static> Connection<UMsg, DMsg> {
         cor_id: CorId,
         msg_options: MsgOptions,
     ) -> Result<CorId, SendUpMsgError> {
-        // ---- RequestInit ----
         #[cfg(feature = "serde-lite")]
         let body = serde_json::to_string(&up_msg.serialize().unwrap_throw()).unwrap_throw();
         #[cfg(feature = "serde")]
         let body = serde_json::to_string(&up_msg).unwrap_throw();
 
-        let mut request_init = RequestInit::new();
-        request_init.method("POST").body(Some(&JsValue::from(body)));
-
-        // ---- Request ----
-        let request =
-            Request::new_with_str_and_init("/_api/up_msg_handler", &request_init).unwrap_throw();
+        let auth_token = self.auth_token(msg_options).await;
 
-        // ---- Headers ----
-        let headers = request.headers();
-        headers
-            .set("X-Correlation-ID", &cor_id.to_string())
-            .unwrap_throw();
-        headers
-            .set("X-Session-ID", &self.session_id.to_string())
-            .unwrap_throw();
+        if let Some(offline_queue) = &self.offline_queue {
+            if offline_queue.should_enqueue() {
+                offline_queue.push(cor_id, body, auth_token);
+                return Ok(cor_id);
+            }
+        }
 
-        if msg_options.auth_token {
-            let auth_token = if let Some(auth_token_getter) = &self.auth_token_getter {
-                auth_token_getter().await
-            } else {
-                None
-            };
-            if let Some(auth_token) = auth_token {
-                headers
-                    .set("X-Auth-Token", auth_token.as_str())
-                    .unwrap_throw();
+        let request_context = self.request_context();
+        let retry_policy = msg_options.retry_policy.unwrap_or(self.retry_policy);
+        let result = retry_policy
+            .run(|| fetch_up_msg(&request_context, cor_id, &body, auth_token.as_ref()))
+            .await;
+
+        match (result, &self.offline_queue) {
+            (Ok(()), _) => Ok(cor_id),
+            (Err(error), Some(offline_queue)) if error.is_retryable() => {
+                offline_queue.push(cor_id, body, auth_token);
+                Ok(cor_id)
             }
+            (Err(error), _) => Err(error),
         }
+    }
 
-        // ---- Response ----
-        let response = JsFuture::from(window().fetch_with_request(&request))
-            .await
-            .map_err(|error| SendUpMsgError::RequestFailed(error))?
-            .unchecked_into::<Response>();
+    async fn auth_token(&self, msg_options: MsgOptions) -> Option<AuthToken> {
+        if !msg_options.auth_token {
+            return None;
+        }
+        match &self.auth_token_getter {
+            Some(auth_token_getter) => auth_token_getter().await,
+            None => None,
+        }
+    }
+
+    pub async fn upload(&self, file: web_sys::File) -> Result<Upload, UploadError> {
+        self.upload_with_options(file, MsgOptions::default()).await
+    }
 
-        if response.ok() {
-            return Ok(cor_id);
+    /// Sends the file to the route created by `moon::upload::route`.
+    pub async fn upload_with_options(
+        &self,
+        file: web_sys::File,
+        msg_options: MsgOptions,
+    ) -> Result<Upload, UploadError> {
+        let cor_id = CorId::new();
+        let file_name = String::from(js_sys::encode_uri_component(&file.name()));
+
+        let (url, custom_headers, headers_getter, with_credentials) = {
+            let api_endpoint = self.api_endpoint.lock().unwrap_throw();
+            (
+                api_endpoint.url(&format!("upload?file_name={}", file_name)),
+                api_endpoint.headers.clone(),
+                api_endpoint.headers_getter.clone(),
+                api_endpoint.with_credentials,
+            )
+        };
+
+        let mut headers = ApiEndpoint::headers(custom_headers, headers_getter).await;
+        headers.push(("X-Correlation-ID".to_owned(), cor_id.to_string()));
+        headers.push(("X-Session-ID".to_owned(), self.session_id.to_string()));
+        if let Some(auth_token) = self.auth_token(msg_options).await {
+            headers.push(("X-Auth-Token".to_owned(), auth_token.into_string()));
         }
-        Err(SendUpMsgError::ResponseIsNot2xx)
+
+        Upload::start(&url, &file, cor_id, &headers, with_credentials)
     }
 
     pub async fn exchange_msgs(&self, up_msg: UMsg) -> Result<(DMsg, CorId), ExchangeMsgsError> {
@@ -175,19 +518,260 @@ impl<UMsg: Serialize, DMsg: DeserializeOwned + 'static> Connection<UMsg, DMsg> {
         up_msg: UMsg,
         msg_options: MsgOptions,
     ) -> Result<(DMsg, CorId), ExchangeMsgsError> {
+        let _in_flight_guard = InFlightGuard::new(&self.in_flight_count);
         let cor_id = CorId::new();
         let (d_msg_sender, d_msg_receiver) = oneshot::channel();
 
         self.d_msg_senders.insert(cor_id, d_msg_sender);
+        let _pending_down_msg = PendingDownMsg {
+            cor_id,
+            d_msg_senders: self.d_msg_senders.clone(),
+            cancel_request_context: msg_options.notify_cancel.then(|| self.request_context()),
+        };
+
+        let exchange = async {
+            self.send_up_msg_with_cor_id_and_options(up_msg, cor_id, msg_options)
+                .await
+                .map_err(ExchangeMsgsError::SendError)?;
+            d_msg_receiver
+                .await
+                .map_err(|_| {
+                    ExchangeMsgsError::ReceiveError(ReceiveDownMsgError::ConnectionClosed)
+                })?
+                .map_err(ExchangeMsgsError::Backend)
+        };
+        let d_msg = match msg_options.timeout {
+            Some(timeout) => {
+                match future::select(Box::pin(exchange), Box::pin(Timer::sleep(timeout))).await {
+                    future::Either::Left((result, _)) => result?,
+                    future::Either::Right(_) => Err(ExchangeMsgsError::Timeout)?,
+                }
+            }
+            None => exchange.await?,
+        };
+        Ok((d_msg, cor_id))
+    }
 
-        self.send_up_msg_with_cor_id_and_options(up_msg, cor_id, msg_options)
+    pub async fn exchange_msgs_stream(
+        &self,
+        up_msg: UMsg,
+    ) -> Result<(impl Stream<Item = DMsg>, CorId), ExchangeMsgsError> {
+        self.exchange_msgs_stream_with_options(up_msg, MsgOptions::default())
             .await
-            .map_err(ExchangeMsgsError::SendError)?;
-        let d_msg = d_msg_receiver
+    }
+
+    /// Receives all `DownMsg`s sent by `SessionActor::send_down_msg_stream` in Moon.
+    /// The stream ends when Moon has sent the last `DownMsg`.
+    pub async fn exchange_msgs_stream_with_options(
+        &self,
+        up_msg: UMsg,
+        msg_options: MsgOptions,
+    ) -> Result<(impl Stream<Item = DMsg>, CorId), ExchangeMsgsError> {
+        let cor_id = CorId::new();
+        let (d_msg_sender, d_msg_receiver) = mpsc::unbounded();
+
+        self.d_msg_stream_senders.insert(cor_id, d_msg_sender);
+
+        if let Err(error) = self
+            .send_up_msg_with_cor_id_and_options(up_msg, cor_id, msg_options)
             .await
-            .map_err(|_| ExchangeMsgsError::ReceiveError(ReceiveDownMsgError::ConnectionClosed))?;
-        Ok((d_msg, cor_id))
+        {
+            self.d_msg_stream_senders.remove(&cor_id);
+            Err(ExchangeMsgsError::SendError(error))?
+        }
+        Ok((d_msg_receiver, cor_id))
+    }
+}
+
+// ------ MsgConnection ------
+
+/// Implemented by `Connection` and `MockConnection`.
+/// Frontend logic written against this trait can be unit-tested without a running Moon.
+pub trait MsgConnection<UMsg, DMsg> {
+    fn send_up_msg_with_options(
+        &self,
+        up_msg: UMsg,
+        msg_options: MsgOptions,
+    ) -> future::LocalBoxFuture<'_, Result<CorId, SendUpMsgError>>;
+
+    fn exchange_msgs_with_options(
+        &self,
+        up_msg: UMsg,
+        msg_options: MsgOptions,
+    ) -> future::LocalBoxFuture<'_, Result<(DMsg, CorId), ExchangeMsgsError>>;
+
+    fn send_up_msg(
+        &self,
+        up_msg: UMsg,
+    ) -> future::LocalBoxFuture<'_, Result<CorId, SendUpMsgError>> {
+        self.send_up_msg_with_options(up_msg, MsgOptions::default())
+    }
+
+    fn exchange_msgs(
+        &self,
+        up_msg: UMsg,
+    ) -> future::LocalBoxFuture<'_, Result<(DMsg, CorId), ExchangeMsgsError>> {
+        self.exchange_msgs_with_options(up_msg, MsgOptions::default())
+    }
+}
+
+impl<UMsg: Serialize + 'static, DMsg: DeserializeOwned + 'static> MsgConnection<UMsg, DMsg>
+    for Connection<UMsg, DMsg>
+{
+    fn send_up_msg_with_options(
+        &self,
+        up_msg: UMsg,
+        msg_options: MsgOptions,
+    ) -> future::LocalBoxFuture<'_, Result<CorId, SendUpMsgError>> {
+        Box::pin(Connection::send_up_msg_with_options(
+            self,
+            up_msg,
+            msg_options,
+        ))
+    }
+
+    fn exchange_msgs_with_options(
+        &self,
+        up_msg: UMsg,
+        msg_options: MsgOptions,
+    ) -> future::LocalBoxFuture<'_, Result<(DMsg, CorId), ExchangeMsgsError>> {
+        Box::pin(Connection::exchange_msgs_with_options(
+            self,
+            up_msg,
+            msg_options,
+        ))
+    }
+}
+
+// ------ ApiEndpoint ------
+
+type HeadersGetter =
+    Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Vec<(String, String)>>>> + Send + Sync>;
+
+// Shared by `Connection` and `OfflineQueue` so builder methods affect both.
+#[derive(Default)]
+struct ApiEndpoint {
+    // Empty for the same origin as the frontend.
+    base_url: String,
+    headers: Vec<(String, String)>,
+    headers_getter: Option<HeadersGetter>,
+    with_credentials: bool,
+}
+
+impl ApiEndpoint {
+    fn url(&self, path: &str) -> String {
+        format!("{}/_api/{}", self.base_url, path)
+    }
+
+    async fn headers(
+        headers: Vec<(String, String)>,
+        headers_getter: Option<HeadersGetter>,
+    ) -> Vec<(String, String)> {
+        match headers_getter {
+            Some(headers_getter) => headers.into_iter().chain(headers_getter().await).collect(),
+            None => headers,
+        }
+    }
+}
+
+// ------ RequestContext ------
+
+#[derive(Clone)]
+struct RequestContext {
+    session_id: SessionId,
+    version_mismatch: Mutable<bool>,
+    api_endpoint: Arc<Mutex<ApiEndpoint>>,
+}
+
+async fn fetch_up_msg(
+    request_context: &RequestContext,
+    cor_id: CorId,
+    body: &str,
+    auth_token: Option<&AuthToken>,
+) -> Result<(), SendUpMsgError> {
+    fetch_api(
+        request_context,
+        "up_msg_handler",
+        cor_id,
+        Some(body),
+        auth_token,
+    )
+    .await
+}
+
+async fn fetch_cancel(
+    request_context: &RequestContext,
+    cor_id: CorId,
+) -> Result<(), SendUpMsgError> {
+    fetch_api(request_context, "cancel_up_msg", cor_id, None, None).await
+}
+
+async fn fetch_api(
+    request_context: &RequestContext,
+    path: &str,
+    cor_id: CorId,
+    body: Option<&str>,
+    auth_token: Option<&AuthToken>,
+) -> Result<(), SendUpMsgError> {
+    // We don't want to hold the lock while waiting for custom headers.
+    let (url, custom_headers, headers_getter, with_credentials) = {
+        let api_endpoint = request_context.api_endpoint.lock().unwrap_throw();
+        (
+            api_endpoint.url(path),
+            api_endpoint.headers.clone(),
+            api_endpoint.headers_getter.clone(),
+            api_endpoint.with_credentials,
+        )
+    };
+
+    // ---- RequestInit ----
+    let mut request_init = RequestInit::new();
+    request_init.method("POST");
+    if let Some(body) = body {
+        request_init.body(Some(&JsValue::from(body)));
+    }
+    if with_credentials {
+        request_init.credentials(RequestCredentials::Include);
+    }
+
+    // ---- Request ----
+    let request = Request::new_with_str_and_init(&url, &request_init)
+        .map_err(SendUpMsgError::RequestFailed)?;
+
+    // ---- Headers ----
+    let headers = request.headers();
+    for (name, value) in ApiEndpoint::headers(custom_headers, headers_getter).await {
+        headers
+            .set(&name, &value)
+            .map_err(SendUpMsgError::RequestFailed)?;
     }
+    headers
+        .set("X-Correlation-ID", &cor_id.to_string())
+        .unwrap_throw();
+    headers
+        .set("X-Session-ID", &request_context.session_id.to_string())
+        .unwrap_throw();
+
+    if let Some(auth_token) = auth_token {
+        headers
+            .set("X-Auth-Token", auth_token.as_str())
+            .unwrap_throw();
+    }
+
+    // ---- Response ----
+    let response = JsFuture::from(window().fetch_with_request(&request))
+        .await
+        .map_err(SendUpMsgError::RequestFailed)?
+        .unchecked_into::<Response>();
+
+    if let Ok(Some(backend_version)) = response.headers().get("X-MoonZoon-Version") {
+        check_version(&request_context.version_mismatch, &backend_version);
+    }
+
+    if response.ok() {
+        return Ok(());
+    }
+    Err(SendUpMsgError::ResponseIsNot2xx(response.status()))
 }
 
 // ------ MsgOptions ------
@@ -195,11 +779,19 @@ impl<UMsg: Serialize, DMsg: DeserializeOwned + 'static> Connection<UMsg, DMsg> {
 #[derive(Debug, Clone, Copy)]
 pub struct MsgOptions {
     auth_token: bool,
+    retry_policy: Option<RetryPolicy>,
+    timeout: Option<u32>,
+    notify_cancel: bool,
 }
 
 impl Default for MsgOptions {
     fn default() -> Self {
-        Self { auth_token: true }
+        Self {
+            auth_token: true,
+            retry_policy: None,
+            timeout: None,
+            notify_cancel: false,
+        }
     }
 }
 
@@ -212,6 +804,27 @@ impl MsgOptions {
         self.auth_token = include;
         self
     }
+
+    /// Overrides the policy set by `Connection::retry_policy`.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// `exchange_msgs` fails with `ExchangeMsgsError::Timeout`
+    /// when the `DownMsg` hasn't arrived in time (in milliseconds).
+    pub fn timeout(mut self, ms: u32) -> Self {
+        self.timeout = Some(ms);
+        self
+    }
+
+    /// Tells Moon that `exchange_msgs` has stopped waiting for the `DownMsg`
+    /// because it timed out or its future has been dropped.
+    /// The `UpMsg` handler can observe it through `UpMsgRequest::cancelled`.
+    pub fn notify_cancel(mut self, notify: bool) -> Self {
+        self.notify_cancel = notify;
+        self
+    }
 }
 
 // ------ SendUpMsgError ------
@@ -219,7 +832,17 @@ impl MsgOptions {
 #[derive(Debug)]
 pub enum SendUpMsgError {
     RequestFailed(JsValue),
-    ResponseIsNot2xx,
+    /// Contains the response status.
+    ResponseIsNot2xx(u16),
+}
+
+impl SendUpMsgError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::RequestFailed(_) => true,
+            Self::ResponseIsNot2xx(status) => matches!(status, 408 | 429 | 500..=599),
+        }
+    }
 }
 
 impl fmt::Display for SendUpMsgError {
@@ -228,8 +851,8 @@ impl fmt::Display for SendUpMsgError {
             Self::RequestFailed(error) => {
                 write!(f, "request failed: {:?}", error)
             }
-            Self::ResponseIsNot2xx => {
-                write!(f, "response status is not 2xx")
+            Self::ResponseIsNot2xx(status) => {
+                write!(f, "response status {} is not 2xx", status)
             }
         }
     }
@@ -262,6 +885,10 @@ impl Error for ReceiveDownMsgError {}
 pub enum ExchangeMsgsError {
     SendError(SendUpMsgError),
     ReceiveError(ReceiveDownMsgError),
+    /// Moon failed to handle the `UpMsg`, e.g. it was invalid or the handler panicked.
+    Backend(ServerError),
+    /// See `MsgOptions::timeout`.
+    Timeout,
 }
 
 impl fmt::Display for ExchangeMsgsError {
@@ -273,6 +900,12 @@ impl fmt::Display for ExchangeMsgsError {
             Self::ReceiveError(error) => {
                 write!(f, "{error}")
             }
+            Self::Backend(error) => {
+                write!(f, "{error}")
+            }
+            Self::Timeout => {
+                write!(f, "DownMsg hasn't arrived in time")
+            }
         }
     }
 }
@@ -0,0 +1,265 @@
+use super::{
+    DMsgSenders, ExchangeMsgsError, MsgConnection, MsgOptions, PendingDownMsg, ReceiveDownMsgError,
+    SendUpMsgError,
+};
+use crate::*;
+use futures_channel::oneshot;
+use moonlight::{CorId, ServerError};
+use std::{collections::VecDeque, sync::Mutex};
+
+// ------ MockResponse ------
+
+/// How `MockConnection` responds to an expected `UpMsg`.
+pub enum MockResponse<DMsg> {
+    /// Moon has accepted the `UpMsg`.
+    /// `exchange_msgs` waits until a `DownMsg` is pushed by `MockConnection::push_down_msg`.
+    Accepted,
+    /// `exchange_msgs` returns the `DownMsg`,
+    /// `send_up_msg` passes it to the `DownMsg` handler.
+    DownMsg(DMsg),
+    /// `exchange_msgs` fails with `ExchangeMsgsError::Backend`,
+    /// `send_up_msg` passes it to the handler set by `MockConnection::server_error_handler`.
+    ServerError(ServerError),
+    /// Moon has responded with the given non-2xx status.
+    Rejected(u16),
+}
+
+// ------ Expectation ------
+
+struct Expectation<UMsg, DMsg> {
+    matcher: Box<dyn Fn(&UMsg) -> bool + Send + Sync>,
+    response: MockResponse<DMsg>,
+}
+
+// ------ Handlers ------
+
+type DownMsgHandler<DMsg> = Box<dyn FnMut(DMsg, CorId) + Send + Sync>;
+type ServerErrorHandler = Box<dyn FnMut(ServerError, CorId) + Send + Sync>;
+
+struct Handlers<DMsg> {
+    down_msg: DownMsgHandler<DMsg>,
+    server_error: Option<ServerErrorHandler>,
+}
+
+enum Event<DMsg> {
+    DownMsg(DMsg, CorId),
+    ServerError(ServerError, CorId),
+}
+
+// ------ MockConnection ------
+
+/// In-memory `MsgConnection` for unit tests, no Moon server is needed.
+///
+/// Expected `UpMsg`s have to be sent in the scripted order, otherwise `MockConnection` panics.
+/// `UpMsg`s are accepted when there are no expectations left. `MsgOptions` are ignored.
+///
+/// ```ignore
+/// connection().expect_up_msg(
+///     |up_msg| matches!(up_msg, UpMsg::SendMessage(_)),
+///     MockResponse::Accepted,
+/// );
+/// send_message().await;
+/// connection().assert_expectations_met();
+/// connection().push_down_msg(DownMsg::MessageReceived(message), CorId::new());
+/// ```
+pub struct MockConnection<UMsg, DMsg> {
+    // `None` while the handlers are being called.
+    handlers: Mutex<Option<Handlers<DMsg>>>,
+    // Events waiting for the handlers, e.g. `DownMsg`s pushed from the `DownMsg` handler.
+    events: Mutex<VecDeque<Event<DMsg>>>,
+    expectations: Mutex<VecDeque<Expectation<UMsg, DMsg>>>,
+    sent_up_msgs: Mutex<Vec<(UMsg, CorId)>>,
+    d_msg_senders: DMsgSenders<oneshot::Sender<Result<DMsg, ServerError>>>,
+}
+
+impl<UMsg, DMsg> MockConnection<UMsg, DMsg> {
+    pub fn new(down_msg_handler: impl FnMut(DMsg, CorId) + Send + Sync + 'static) -> Self {
+        Self {
+            handlers: Mutex::new(Some(Handlers {
+                down_msg: Box::new(down_msg_handler),
+                server_error: None,
+            })),
+            events: Mutex::new(VecDeque::new()),
+            expectations: Mutex::new(VecDeque::new()),
+            sent_up_msgs: Mutex::new(Vec::new()),
+            d_msg_senders: DMsgSenders::new(),
+        }
+    }
+
+    /// Receives `ServerError`s without a pending `exchange_msgs` call,
+    /// e.g. `MockResponse::ServerError` for `send_up_msg`.
+    /// `MockConnection` panics on such errors when the handler isn't set.
+    pub fn server_error_handler(
+        self,
+        server_error_handler: impl FnMut(ServerError, CorId) + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers
+            .lock()
+            .unwrap_throw()
+            .as_mut()
+            .unwrap_throw()
+            .server_error = Some(Box::new(server_error_handler));
+        self
+    }
+
+    /// The next sent `UpMsg` has to satisfy `matcher`.
+    pub fn expect_up_msg(
+        &self,
+        matcher: impl Fn(&UMsg) -> bool + Send + Sync + 'static,
+        response: MockResponse<DMsg>,
+    ) {
+        self.expectations
+            .lock()
+            .unwrap_throw()
+            .push_back(Expectation {
+                matcher: Box::new(matcher),
+                response,
+            });
+    }
+
+    /// Panics when some expected `UpMsg`s haven't been sent.
+    pub fn assert_expectations_met(&self) {
+        let remaining = self.expectations.lock().unwrap_throw().len();
+        if remaining > 0 {
+            panic!("{} expected UpMsg(s) haven't been sent", remaining);
+        }
+    }
+
+    /// Returns all `UpMsg`s sent since the last call.
+    pub fn take_sent_up_msgs(&self) -> Vec<(UMsg, CorId)> {
+        std::mem::take(&mut *self.sent_up_msgs.lock().unwrap_throw())
+    }
+
+    /// Simulates a `DownMsg` sent by Moon.
+    /// It's returned from the pending `exchange_msgs` call with the same `CorId`
+    /// or passed to the `DownMsg` handler.
+    pub fn push_down_msg(&self, d_msg: DMsg, cor_id: CorId) {
+        if let Some(d_msg_sender) = self.d_msg_senders.remove(&cor_id) {
+            if let Err(Ok(d_msg)) = d_msg_sender.send(Ok(d_msg)) {
+                self.handle_event(Event::DownMsg(d_msg, cor_id));
+            }
+            return;
+        }
+        self.handle_event(Event::DownMsg(d_msg, cor_id))
+    }
+
+    /// Fails the pending `exchange_msgs` call with the same `CorId`
+    /// or passes the error to the handler set by `server_error_handler`.
+    pub fn push_server_error(&self, error: ServerError, cor_id: CorId) {
+        if let Some(d_msg_sender) = self.d_msg_senders.remove(&cor_id) {
+            let _ = d_msg_sender.send(Err(error));
+            return;
+        }
+        self.handle_event(Event::ServerError(error, cor_id))
+    }
+
+    // Handlers are called without holding any lock, so they can push other events.
+    // Events pushed while the handlers are being called are handled by the outer call.
+    fn handle_event(&self, event: Event<DMsg>) {
+        self.events.lock().unwrap_throw().push_back(event);
+        loop {
+            let mut handlers = match self.handlers.lock().unwrap_throw().take() {
+                Some(handlers) => handlers,
+                None => return,
+            };
+            loop {
+                let event = self.events.lock().unwrap_throw().pop_front();
+                match event {
+                    Some(Event::DownMsg(d_msg, cor_id)) => (handlers.down_msg)(d_msg, cor_id),
+                    Some(Event::ServerError(error, cor_id)) => {
+                        if let Some(server_error_handler) = &mut handlers.server_error {
+                            server_error_handler(error, cor_id);
+                            continue;
+                        }
+                        *self.handlers.lock().unwrap_throw() = Some(handlers);
+                        panic!("UpMsg '{cor_id}' failed with unhandled ServerError: {error}");
+                    }
+                    None => break,
+                }
+            }
+            *self.handlers.lock().unwrap_throw() = Some(handlers);
+            if self.events.lock().unwrap_throw().is_empty() {
+                return;
+            }
+        }
+    }
+
+    fn handle_up_msg(&self, up_msg: UMsg, cor_id: CorId) -> MockResponse<DMsg> {
+        let expectation = self.expectations.lock().unwrap_throw().pop_front();
+        let response = match expectation {
+            Some(Expectation { matcher, response }) => {
+                if !matcher(&up_msg) {
+                    panic!("UpMsg '{}' doesn't match the expected one", cor_id);
+                }
+                response
+            }
+            None => MockResponse::Accepted,
+        };
+        self.sent_up_msgs
+            .lock()
+            .unwrap_throw()
+            .push((up_msg, cor_id));
+        response
+    }
+}
+
+impl<UMsg: 'static, DMsg: 'static> MsgConnection<UMsg, DMsg> for MockConnection<UMsg, DMsg> {
+    fn send_up_msg_with_options(
+        &self,
+        up_msg: UMsg,
+        _: MsgOptions,
+    ) -> future::LocalBoxFuture<'_, Result<CorId, SendUpMsgError>> {
+        let cor_id = CorId::new();
+        let result = match self.handle_up_msg(up_msg, cor_id) {
+            MockResponse::Accepted => Ok(cor_id),
+            MockResponse::DownMsg(d_msg) => {
+                self.push_down_msg(d_msg, cor_id);
+                Ok(cor_id)
+            }
+            MockResponse::ServerError(error) => {
+                self.push_server_error(error, cor_id);
+                Ok(cor_id)
+            }
+            MockResponse::Rejected(status) => Err(SendUpMsgError::ResponseIsNot2xx(status)),
+        };
+        Box::pin(future::ready(result))
+    }
+
+    fn exchange_msgs_with_options(
+        &self,
+        up_msg: UMsg,
+        _: MsgOptions,
+    ) -> future::LocalBoxFuture<'_, Result<(DMsg, CorId), ExchangeMsgsError>> {
+        let cor_id = CorId::new();
+        let d_msg_receiver = match self.handle_up_msg(up_msg, cor_id) {
+            MockResponse::Accepted => {
+                let (d_msg_sender, d_msg_receiver) = oneshot::channel();
+                self.d_msg_senders.insert(cor_id, d_msg_sender);
+                d_msg_receiver
+            }
+            MockResponse::DownMsg(d_msg) => return Box::pin(future::ready(Ok((d_msg, cor_id)))),
+            MockResponse::ServerError(error) => {
+                return Box::pin(future::ready(Err(ExchangeMsgsError::Backend(error))))
+            }
+            MockResponse::Rejected(status) => {
+                let error = SendUpMsgError::ResponseIsNot2xx(status);
+                return Box::pin(future::ready(Err(ExchangeMsgsError::SendError(error))));
+            }
+        };
+        let pending_down_msg = PendingDownMsg {
+            cor_id,
+            d_msg_senders: self.d_msg_senders.clone(),
+            cancel_request_context: None,
+        };
+        Box::pin(async move {
+            let _pending_down_msg = pending_down_msg;
+            let d_msg = d_msg_receiver
+                .await
+                .map_err(|_| {
+                    ExchangeMsgsError::ReceiveError(ReceiveDownMsgError::ConnectionClosed)
+                })?
+                .map_err(ExchangeMsgsError::Backend)?;
+            Ok((d_msg, cor_id))
+        })
+    }
+}
@@ -0,0 +1,191 @@
+use super::{fetch_up_msg, RequestContext};
+use crate::*;
+use moonlight::{AuthToken, CorId};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+// The persisted session id is appended so each tab has its own queue
+// and a reloaded tab finds its queued `UpMsg`s.
+const STORAGE_KEY_PREFIX: &str = "moonzoon_up_msg_queue_";
+
+// How often queued `UpMsg`s are resent while the browser is online but Moon is unreachable.
+const FLUSH_RETRY_INTERVAL: u32 = 5_000;
+
+// ------ QueuedUpMsg ------
+
+#[derive(Clone)]
+#[cfg_attr(feature = "web_storage", derive(Serialize, Deserialize))]
+struct QueuedUpMsg {
+    cor_id: String,
+    body: String,
+    auth_token: Option<String>,
+}
+
+// ------ OfflineQueue ------
+
+/// `UpMsg`s that couldn't be sent are kept in `local_storage()`
+/// and sent in the original order once the browser is online again.
+///
+/// The queue belongs to the tab's session, so other tabs never send its `UpMsg`s
+/// and Moon can deduplicate retries by `CorId`.
+pub(super) struct OfflineQueue {
+    inner: Arc<Inner>,
+    online_listener: SendWrapper<Closure<dyn Fn()>>,
+}
+
+struct Inner {
+    request_context: RequestContext,
+    #[cfg_attr(not(feature = "web_storage"), allow(dead_code))]
+    storage_key: String,
+    up_msgs: Mutex<VecDeque<QueuedUpMsg>>,
+    flushing: AtomicBool,
+}
+
+impl OfflineQueue {
+    pub(super) fn new(request_context: RequestContext) -> Self {
+        let storage_key = [STORAGE_KEY_PREFIX, &request_context.session_id.to_string()].concat();
+        let inner = Arc::new(Inner {
+            up_msgs: Mutex::new(load(&storage_key)),
+            request_context,
+            storage_key,
+            flushing: AtomicBool::new(false),
+        });
+
+        let online_listener = Closure::new({
+            let inner = Arc::clone(&inner);
+            move || Task::start(flush(Arc::clone(&inner)))
+        });
+        window()
+            .add_event_listener_with_callback("online", online_listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+
+        // `UpMsg`s queued before the page has been reloaded.
+        Task::start(flush(Arc::clone(&inner)));
+
+        Self {
+            inner,
+            online_listener: SendWrapper::new(online_listener),
+        }
+    }
+
+    /// New `UpMsg`s have to wait for the queued ones to preserve the order.
+    pub(super) fn should_enqueue(&self) -> bool {
+        !is_online() || !self.inner.up_msgs.lock().unwrap_throw().is_empty()
+    }
+
+    pub(super) fn push(&self, cor_id: CorId, body: String, auth_token: Option<AuthToken>) {
+        {
+            let cor_id = cor_id.to_string();
+            let mut up_msgs = self.inner.up_msgs.lock().unwrap_throw();
+            // E.g. `exchange_msgs` failed after the `UpMsg` had been already queued.
+            if up_msgs.iter().all(|up_msg| up_msg.cor_id != cor_id) {
+                up_msgs.push_back(QueuedUpMsg {
+                    cor_id,
+                    body,
+                    auth_token: auth_token.map(AuthToken::into_string),
+                });
+                persist(&self.inner, &up_msgs);
+            }
+        }
+        Task::start(flush(Arc::clone(&self.inner)));
+    }
+}
+
+impl Drop for OfflineQueue {
+    fn drop(&mut self) {
+        let _ = window().remove_event_listener_with_callback(
+            "online",
+            self.online_listener.as_ref().unchecked_ref(),
+        );
+    }
+}
+
+async fn flush(inner: Arc<Inner>) {
+    if inner.flushing.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    loop {
+        let up_msg = match inner.up_msgs.lock().unwrap_throw().front().cloned() {
+            Some(up_msg) => up_msg,
+            None => break,
+        };
+        let cor_id = match up_msg.cor_id.parse() {
+            Ok(cor_id) => cor_id,
+            Err(_) => {
+                remove_front(&inner);
+                continue;
+            }
+        };
+        let auth_token = up_msg.auth_token.map(AuthToken::new);
+        match fetch_up_msg(
+            &inner.request_context,
+            cor_id,
+            &up_msg.body,
+            auth_token.as_ref(),
+        )
+        .await
+        {
+            Ok(()) => remove_front(&inner),
+            Err(error) if error.is_retryable() => {
+                // The `online` listener starts the flush again.
+                if !is_online() {
+                    break;
+                }
+                Timer::sleep(FLUSH_RETRY_INTERVAL).await;
+            }
+            Err(error) => {
+                crate::eprintln!("Queued UpMsg '{}' failed: {}", cor_id, error);
+                remove_front(&inner);
+            }
+        }
+    }
+    inner.flushing.store(false, Ordering::SeqCst);
+}
+
+fn remove_front(inner: &Inner) {
+    let mut up_msgs = inner.up_msgs.lock().unwrap_throw();
+    up_msgs.pop_front();
+    persist(inner, &up_msgs);
+}
+
+fn is_online() -> bool {
+    window().navigator().on_line()
+}
+
+// ------ persistence ------
+
+#[cfg(feature = "web_storage")]
+fn load(storage_key: &str) -> VecDeque<QueuedUpMsg> {
+    match LocalStorage::try_new() {
+        Ok(storage) => match storage.get(storage_key) {
+            Some(Ok(up_msgs)) => up_msgs,
+            _ => VecDeque::new(),
+        },
+        Err(_) => VecDeque::new(),
+    }
+}
+
+#[cfg(not(feature = "web_storage"))]
+fn load(_: &str) -> VecDeque<QueuedUpMsg> {
+    VecDeque::new()
+}
+
+// The queue is kept only in memory when the storage isn't available.
+#[cfg(feature = "web_storage")]
+fn persist(inner: &Inner, up_msgs: &VecDeque<QueuedUpMsg>) {
+    if let Ok(storage) = LocalStorage::try_new() {
+        if up_msgs.is_empty() {
+            storage.remove(&inner.storage_key);
+        } else {
+            let _ = storage.insert(&inner.storage_key, up_msgs);
+        }
+    }
+}
+
+#[cfg(not(feature = "web_storage"))]
+fn persist(_: &Inner, _: &VecDeque<QueuedUpMsg>) {}
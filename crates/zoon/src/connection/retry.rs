@@ -0,0 +1,102 @@
+use super::SendUpMsgError;
+use crate::*;
+
+// ------ RetryPolicy ------
+
+/// Failed `UpMsg` requests are retried with exponential backoff.
+/// All attempts share the same `CorId` so Moon handles the `UpMsg` only once.
+///
+/// Only network errors and `5xx`, `408` and `429` responses are retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_delay: u32,
+    max_delay: u32,
+    multiplier: f64,
+    jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: 200,
+            max_delay: 10_000,
+            multiplier: 2.,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only one attempt is made.
+    pub fn no_retry() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Includes the first attempt.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// The delay before the first retry in milliseconds.
+    pub fn initial_delay(mut self, ms: u32) -> Self {
+        self.initial_delay = ms;
+        self
+    }
+
+    /// In milliseconds.
+    pub fn max_delay(mut self, ms: u32) -> Self {
+        self.max_delay = ms;
+        self
+    }
+
+    /// Each next delay is `multiplier` times longer.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Randomizes delays so many clients don't retry at the same moment
+    /// after a server outage.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    // `retry` starts from 1.
+    fn delay(&self, retry: u32) -> u32 {
+        let exponent = i32::try_from(retry - 1).unwrap_or(i32::MAX);
+        let delay = (f64::from(self.initial_delay) * self.multiplier.powi(exponent))
+            .min(f64::from(self.max_delay));
+        if self.jitter {
+            // "Full jitter", i.e. a random delay between 0 and the computed one.
+            return (delay * js_sys::Math::random()) as u32;
+        }
+        delay as u32
+    }
+
+    pub(super) async fn run<T, F>(
+        &self,
+        mut attempt: impl FnMut() -> F,
+    ) -> Result<T, SendUpMsgError>
+    where
+        F: Future<Output = Result<T, SendUpMsgError>>,
+    {
+        let mut retry = 0;
+        loop {
+            match attempt().await {
+                Err(error) if error.is_retryable() && retry + 1 < self.max_attempts => {
+                    retry += 1;
+                    Timer::sleep(self.delay(retry)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
@@ -1,65 +1,190 @@
-use crate::moonlight::{serde_json, CorId, DeserializeOwned, DownMsgTransporterForDe, SessionId};
-use crate::{format, *};
-use std::{error::Error, fmt};
+use super::ConnectionStatus;
+use crate::moonlight::{serde_json, CorId, DeserializeOwned, DownMsgTransporterForDe, ServerError};
+use crate::*;
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+};
 
 #[cfg(feature = "moonlight/serde_with_serde")]
 use moonlight::serde::{self, Deserialize};
 
 // ------ SSE ------
 
+// The `EventSource` is opened in the next microtask so the `Connection` builder methods
+// called right after `Connection::new` (e.g. `base_url`) don't open and close extra connections.
 pub struct SSE {
-    reconnecting_event_source: SendWrapper<ReconnectingEventSource>,
-    _down_msg_handler: SendWrapper<Closure<dyn FnMut(JsValue)>>,
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    reconnecting_event_source: Mutex<Option<SendWrapper<ReconnectingEventSource>>>,
+    target: Mutex<Target>,
+    down_msg_handler: SendWrapper<Closure<dyn FnMut(JsValue)>>,
+    down_msg_stream_handler: SendWrapper<Closure<dyn FnMut(JsValue)>>,
+    down_msg_stream_end_handler: SendWrapper<Closure<dyn FnMut(JsValue)>>,
+    version_handler: SendWrapper<Closure<dyn FnMut(JsValue)>>,
+    open_handler: SendWrapper<Closure<dyn FnMut(JsValue)>>,
+    error_handler: SendWrapper<Closure<dyn FnMut(JsValue)>>,
+    status: Mutable<ConnectionStatus>,
+}
+
+#[derive(PartialEq)]
+struct Target {
+    url: String,
+    with_credentials: bool,
 }
 
 impl Drop for SSE {
     fn drop(&mut self) {
-        self.reconnecting_event_source.close();
+        if let Some(reconnecting_event_source) = self.inner.event_source().take() {
+            reconnecting_event_source.close();
+        }
+        self.inner.status.set_neq(ConnectionStatus::Closed);
     }
 }
 
 impl SSE {
     #[cfg(feature = "serde")]
     pub fn new<DMsg: DeserializeOwned>(
-        session_id: SessionId,
+        url: &str,
         down_msg_handler: impl FnMut(DMsg, CorId) + 'static,
+        down_msg_stream_handler: impl FnMut(DMsg, CorId) + 'static,
+        down_msg_stream_end_handler: impl FnMut(CorId) + 'static,
+        server_error_handler: impl FnMut(ServerError, CorId) + Clone + 'static,
+        version_handler: impl FnMut(String) + 'static,
+        status: Mutable<ConnectionStatus>,
     ) -> Self {
-        let down_msg_handler = down_msg_handler_closure(down_msg_handler);
-
-        let reconnecting_event_source = connect(session_id);
-        reconnecting_event_source
-            .add_event_listener("down_msg", down_msg_handler.as_ref().unchecked_ref());
-
-        Self {
-            reconnecting_event_source: SendWrapper::new(reconnecting_event_source),
-            _down_msg_handler: SendWrapper::new(down_msg_handler),
-        }
+        Self::from_handler_closures(
+            url,
+            down_msg_handler_closure(down_msg_handler, server_error_handler.clone()),
+            down_msg_handler_closure(down_msg_stream_handler, server_error_handler),
+            down_msg_stream_end_handler,
+            version_handler,
+            status,
+        )
     }
     #[cfg(feature = "serde-lite")]
     pub fn new<DMsg: Deserialize>(
-        session_id: SessionId,
+        url: &str,
         down_msg_handler: impl FnMut(DMsg, CorId) + 'static,
+        down_msg_stream_handler: impl FnMut(DMsg, CorId) + 'static,
+        down_msg_stream_end_handler: impl FnMut(CorId) + 'static,
+        server_error_handler: impl FnMut(ServerError, CorId) + Clone + 'static,
+        version_handler: impl FnMut(String) + 'static,
+        status: Mutable<ConnectionStatus>,
     ) -> Self {
-        let down_msg_handler = down_msg_handler_closure(down_msg_handler);
+        Self::from_handler_closures(
+            url,
+            down_msg_handler_closure(down_msg_handler, server_error_handler.clone()),
+            down_msg_handler_closure(down_msg_stream_handler, server_error_handler),
+            down_msg_stream_end_handler,
+            version_handler,
+            status,
+        )
+    }
 
-        let reconnecting_event_source = connect(session_id);
-        reconnecting_event_source
-            .add_event_listener("down_msg", down_msg_handler.as_ref().unchecked_ref());
+    fn from_handler_closures(
+        url: &str,
+        down_msg_handler: Closure<dyn FnMut(JsValue)>,
+        down_msg_stream_handler: Closure<dyn FnMut(JsValue)>,
+        down_msg_stream_end_handler: impl FnMut(CorId) + 'static,
+        version_handler: impl FnMut(String) + 'static,
+        status: Mutable<ConnectionStatus>,
+    ) -> Self {
+        let inner = Arc::new(Inner {
+            reconnecting_event_source: Mutex::new(None),
+            target: Mutex::new(Target {
+                url: url.to_owned(),
+                with_credentials: false,
+            }),
+            down_msg_handler: SendWrapper::new(down_msg_handler),
+            down_msg_stream_handler: SendWrapper::new(down_msg_stream_handler),
+            down_msg_stream_end_handler: SendWrapper::new(cor_id_handler_closure(
+                down_msg_stream_end_handler,
+            )),
+            version_handler: SendWrapper::new(version_handler_closure(version_handler)),
+            open_handler: SendWrapper::new(open_handler_closure(status.clone())),
+            error_handler: SendWrapper::new(error_handler_closure(status.clone())),
+            status,
+        });
+        Task::start({
+            let inner = Arc::downgrade(&inner);
+            async move {
+                if let Some(inner) = inner.upgrade() {
+                    inner.connect();
+                }
+            }
+        });
+        Self { inner }
+    }
+
+    /// Connects to `url` if it or `with_credentials` has changed.
+    /// The current connection is closed only when it has been already opened.
+    pub fn reconnect(&mut self, url: &str, with_credentials: bool) {
+        let target = Target {
+            url: url.to_owned(),
+            with_credentials,
+        };
+        {
+            let mut current_target = self.inner.target.lock().unwrap_throw();
+            if *current_target == target {
+                return;
+            }
+            *current_target = target;
+        }
+        if let Some(reconnecting_event_source) = self.inner.event_source().take() {
+            reconnecting_event_source.close();
+            self.inner.status.set_neq(ConnectionStatus::Connecting);
+            self.inner.connect();
+        }
+    }
+}
 
-        Self {
-            reconnecting_event_source: SendWrapper::new(reconnecting_event_source),
-            _down_msg_handler: SendWrapper::new(down_msg_handler),
+impl Inner {
+    fn event_source(&self) -> MutexGuard<Option<SendWrapper<ReconnectingEventSource>>> {
+        self.reconnecting_event_source.lock().unwrap_throw()
+    }
+
+    fn connect(&self) {
+        let mut reconnecting_event_source = self.event_source();
+        if reconnecting_event_source.is_some() {
+            return;
+        }
+        let new_reconnecting_event_source = {
+            let target = self.target.lock().unwrap_throw();
+            connect(&target.url, target.with_credentials)
+        };
+        let listeners = [
+            ("down_msg", &self.down_msg_handler),
+            ("down_msg_stream", &self.down_msg_stream_handler),
+            ("down_msg_stream_end", &self.down_msg_stream_end_handler),
+            ("version", &self.version_handler),
+            ("open", &self.open_handler),
+            ("error", &self.error_handler),
+        ];
+        for (event, listener) in listeners {
+            new_reconnecting_event_source
+                .add_event_listener(event, listener.as_ref().unchecked_ref());
         }
+        *reconnecting_event_source = Some(SendWrapper::new(new_reconnecting_event_source));
     }
 }
 
 #[cfg(feature = "serde")]
 fn down_msg_handler_closure<DMsg: DeserializeOwned>(
     mut down_msg_handler: impl FnMut(DMsg, CorId) + 'static,
+    mut server_error_handler: impl FnMut(ServerError, CorId) + 'static,
 ) -> Closure<dyn FnMut(JsValue)> {
     Closure::new(
         move |event: JsValue| match down_msg_transporter_from_event(event) {
-            Ok(DownMsgTransporterForDe { down_msg, cor_id }) => down_msg_handler(down_msg, cor_id),
+            Ok(DownMsgTransporterForDe::Ok { down_msg, cor_id }) => {
+                down_msg_handler(down_msg, cor_id)
+            }
+            Ok(DownMsgTransporterForDe::Err { error, cor_id }) => {
+                server_error_handler(error, cor_id)
+            }
             Err(error) => crate::eprintln!("{:?}", error),
         },
     )
@@ -67,15 +192,67 @@ fn down_msg_handler_closure<DMsg: DeserializeOwned>(
 #[cfg(feature = "serde-lite")]
 fn down_msg_handler_closure<DMsg: Deserialize>(
     mut down_msg_handler: impl FnMut(DMsg, CorId) + 'static,
+    mut server_error_handler: impl FnMut(ServerError, CorId) + 'static,
 ) -> Closure<dyn FnMut(JsValue)> {
     Closure::new(
         move |event: JsValue| match down_msg_transporter_from_event(event) {
-            Ok(DownMsgTransporterForDe { down_msg, cor_id }) => down_msg_handler(down_msg, cor_id),
+            Ok(DownMsgTransporterForDe::Ok { down_msg, cor_id }) => {
+                down_msg_handler(down_msg, cor_id)
+            }
+            Ok(DownMsgTransporterForDe::Err { error, cor_id }) => {
+                server_error_handler(error, cor_id)
+            }
             Err(error) => crate::eprintln!("{:?}", error),
         },
     )
 }
 
+fn cor_id_handler_closure(
+    mut cor_id_handler: impl FnMut(CorId) + 'static,
+) -> Closure<dyn FnMut(JsValue)> {
+    Closure::new(move |event: JsValue| match cor_id_from_event(event) {
+        Ok(cor_id) => cor_id_handler(cor_id),
+        Err(error) => crate::eprintln!("{:?}", error),
+    })
+}
+
+fn version_handler_closure(
+    mut version_handler: impl FnMut(String) + 'static,
+) -> Closure<dyn FnMut(JsValue)> {
+    Closure::new(move |event: JsValue| {
+        let version = Reflect::get(&event, &JsValue::from("data"))
+            .unwrap()
+            .as_string();
+        match version {
+            Some(version) => version_handler(version),
+            None => crate::eprintln!("{:?}", DownMsgError::InvalidDataValue),
+        }
+    })
+}
+
+fn open_handler_closure(status: Mutable<ConnectionStatus>) -> Closure<dyn FnMut(JsValue)> {
+    Closure::new(move |_| status.set_neq(ConnectionStatus::Open))
+}
+
+// `ReconnectingEventSource` fires `error` and then tries to connect again.
+fn error_handler_closure(status: Mutable<ConnectionStatus>) -> Closure<dyn FnMut(JsValue)> {
+    Closure::new(move |_| {
+        status.update(|status| match status {
+            ConnectionStatus::Open => ConnectionStatus::Reconnecting,
+            status => status,
+        })
+    })
+}
+
+fn cor_id_from_event(event: JsValue) -> Result<CorId, DownMsgError> {
+    Reflect::get(&event, &JsValue::from("data"))
+        .unwrap()
+        .as_string()
+        .ok_or(DownMsgError::InvalidDataValue)?
+        .parse()
+        .map_err(|_| DownMsgError::InvalidCorId)
+}
+
 #[cfg(feature = "serde")]
 fn down_msg_transporter_from_event<DMsg: DeserializeOwned>(
     event: JsValue,
@@ -103,11 +280,11 @@ fn down_msg_transporter_from_event<DMsg: Deserialize>(
     .map_err(DownMsgError::DeserializationFailed)
 }
 
-fn connect(session_id: SessionId) -> ReconnectingEventSource {
+fn connect(url: &str, with_credentials: bool) -> ReconnectingEventSource {
     ReconnectingEventSource::new(
-        &format!("/_api/message_sse/{}", session_id),
+        url,
         Some(ReconnectingEventSourceOptions {
-            withCredentials: false,
+            withCredentials: with_credentials,
             max_retry_time: 5000,
         }),
     )
@@ -118,6 +295,7 @@ fn connect(session_id: SessionId) -> ReconnectingEventSource {
 #[derive(Debug)]
 enum DownMsgError {
     InvalidDataValue,
+    InvalidCorId,
     JsonDeserializationFailed(serde_json::Error),
     #[cfg(feature = "serde-lite")]
     DeserializationFailed(serde_lite::Error),
@@ -129,6 +307,9 @@ impl fmt::Display for DownMsgError {
             DownMsgError::InvalidDataValue => {
                 write!(f, "invalid DownMsg data value")
             }
+            DownMsgError::InvalidCorId => {
+                write!(f, "invalid CorId in the DownMsg stream end")
+            }
             DownMsgError::JsonDeserializationFailed(error) => {
                 write!(
                     f,
@@ -0,0 +1,82 @@
+use crate::*;
+
+// ------ ConnectionStatus ------
+
+/// The state of the connection receiving messages from Moon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// The first connection attempt hasn't finished yet.
+    Connecting,
+    Open,
+    /// The connection has been lost, the browser is offline
+    /// or a new connection attempt is in progress.
+    Reconnecting,
+    /// The `Connection` has been dropped.
+    Closed,
+}
+
+// ------ OnlineStatus ------
+
+/// Follows `navigator.onLine`.
+pub(super) struct OnlineStatus {
+    online: Mutable<bool>,
+    online_listener: SendWrapper<Closure<dyn Fn()>>,
+    offline_listener: SendWrapper<Closure<dyn Fn()>>,
+}
+
+impl OnlineStatus {
+    pub(super) fn new() -> Self {
+        let online = Mutable::new(window().navigator().on_line());
+        let online_listener = listener("online", online.clone(), true);
+        let offline_listener = listener("offline", online.clone(), false);
+        Self {
+            online,
+            online_listener,
+            offline_listener,
+        }
+    }
+
+    pub(super) fn signal(&self) -> impl Signal<Item = bool> {
+        self.online.signal()
+    }
+}
+
+impl Drop for OnlineStatus {
+    fn drop(&mut self) {
+        let window = window();
+        let _ = window.remove_event_listener_with_callback(
+            "online",
+            self.online_listener.as_ref().unchecked_ref(),
+        );
+        let _ = window.remove_event_listener_with_callback(
+            "offline",
+            self.offline_listener.as_ref().unchecked_ref(),
+        );
+    }
+}
+
+fn listener(event: &str, online: Mutable<bool>, value: bool) -> SendWrapper<Closure<dyn Fn()>> {
+    let closure = Closure::new(move || online.set_neq(value));
+    window()
+        .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+        .unwrap_throw();
+    SendWrapper::new(closure)
+}
+
+// ------ InFlightGuard ------
+
+/// Counts in-flight requests, the count is decremented even when the request future is dropped.
+pub(super) struct InFlightGuard(Mutable<usize>);
+
+impl InFlightGuard {
+    pub(super) fn new(count: &Mutable<usize>) -> Self {
+        count.update(|count| count + 1);
+        Self(count.clone())
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.update(|count| count.saturating_sub(1));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::*;
+use std::{error::Error, fmt};
+use web_sys::{File, ProgressEvent, XmlHttpRequest};
+
+// ------ UploadProgress ------
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UploadProgress {
+    pub loaded_bytes: f64,
+    pub total_bytes: f64,
+}
+
+impl UploadProgress {
+    /// Returns a number in the range `0.0..=100.0`.
+    pub fn percent(&self) -> f64 {
+        if self.total_bytes <= 0. {
+            return 0.;
+        }
+        self.loaded_bytes / self.total_bytes * 100.
+    }
+}
+
+// ------ Upload ------
+
+pub struct Upload {
+    cor_id: CorId,
+    xhr: SendWrapper<XmlHttpRequest>,
+    progress: Mutable<UploadProgress>,
+    cancelled: Mutable<bool>,
+    result_receiver: oneshot::Receiver<Result<(), UploadError>>,
+}
+
+impl Upload {
+    pub(super) fn start(
+        url: &str,
+        file: &File,
+        cor_id: CorId,
+        headers: &[(String, String)],
+        with_credentials: bool,
+    ) -> Result<Self, UploadError> {
+        let xhr = XmlHttpRequest::new().map_err(UploadError::RequestFailed)?;
+        xhr.open_with_async("POST", url, true)
+            .map_err(UploadError::RequestFailed)?;
+        xhr.set_with_credentials(with_credentials);
+        for (name, value) in headers {
+            xhr.set_request_header(name, value)
+                .map_err(UploadError::RequestFailed)?;
+        }
+        let file_type = file.type_();
+        if !file_type.is_empty() {
+            xhr.set_request_header("Content-Type", &file_type)
+                .map_err(UploadError::RequestFailed)?;
+        }
+
+        let progress = Mutable::new(UploadProgress {
+            loaded_bytes: 0.,
+            total_bytes: file.size(),
+        });
+        let cancelled = Mutable::new(false);
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        // Closures are owned by JS to keep them alive even when `Upload` is dropped.
+        let progress_handler = Closure::<dyn FnMut(ProgressEvent)>::new({
+            let progress = progress.clone();
+            move |event: ProgressEvent| {
+                if event.length_computable() {
+                    progress.set(UploadProgress {
+                        loaded_bytes: event.loaded(),
+                        total_bytes: event.total(),
+                    });
+                }
+            }
+        })
+        .into_js_value();
+        xhr.upload()
+            .map_err(UploadError::RequestFailed)?
+            .add_event_listener_with_callback("progress", progress_handler.unchecked_ref())
+            .map_err(UploadError::RequestFailed)?;
+
+        let loadend_handler = Closure::once_into_js({
+            let xhr = xhr.clone();
+            let cancelled = cancelled.clone();
+            move || {
+                let result = match xhr.status().unwrap_or_default() {
+                    _ if cancelled.get() => Err(UploadError::Cancelled),
+                    0 => Err(UploadError::RequestFailed(JsValue::from("network error"))),
+                    200..=299 => Ok(()),
+                    status => Err(UploadError::ResponseIsNot2xx(status)),
+                };
+                let _ = result_sender.send(result);
+            }
+        });
+        xhr.add_event_listener_with_callback("loadend", loadend_handler.unchecked_ref())
+            .map_err(UploadError::RequestFailed)?;
+
+        xhr.send_with_opt_blob(Some(file.as_ref()))
+            .map_err(UploadError::RequestFailed)?;
+
+        Ok(Self {
+            cor_id,
+            xhr: SendWrapper::new(xhr),
+            progress,
+            cancelled,
+            result_receiver,
+        })
+    }
+
+    pub fn cor_id(&self) -> CorId {
+        self.cor_id
+    }
+
+    pub fn progress_signal(&self) -> impl Signal<Item = UploadProgress> {
+        self.progress.signal()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.set(true);
+        let _ = self.xhr.abort();
+    }
+
+    /// Waits until the file is uploaded and processed by the upload handler in Moon.
+    pub async fn finished(self) -> Result<CorId, UploadError> {
+        let cor_id = self.cor_id;
+        self.result_receiver
+            .await
+            .map_err(|_| UploadError::Cancelled)??;
+        Ok(cor_id)
+    }
+}
+
+// ------ UploadError ------
+
+#[derive(Debug)]
+pub enum UploadError {
+    RequestFailed(JsValue),
+    ResponseIsNot2xx(u16),
+    Cancelled,
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RequestFailed(error) => {
+                write!(f, "upload request failed: {:?}", error)
+            }
+            Self::ResponseIsNot2xx(status) => {
+                write!(f, "upload response status is {status}, not 2xx")
+            }
+            Self::Cancelled => {
+                write!(f, "upload cancelled")
+            }
+        }
+    }
+}
+
+impl Error for UploadError {}
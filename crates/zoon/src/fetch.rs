@@ -0,0 +1,569 @@
+//! A small HTTP client for endpoints not handled by `Connection`,
+//! e.g. custom Moon routes or third-party APIs. Relative URLs are supported.
+//!
+//! ```ignore
+//! async fn stars_request() -> Result<u32, fetch::FetchError> {
+//!     Ok(fetch::Request::get("/_api/moonzoon_stars")
+//!         .timeout(10_000)
+//!         .send()
+//!         .await?
+//!         .error_for_status()?
+//!         .text()
+//!         .await?
+//!         .parse()
+//!         .unwrap_throw())
+//! }
+//! ```
+
+use crate::*;
+use std::{
+    error::Error,
+    fmt,
+    string::FromUtf8Error,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use web_sys::{
+    AbortController, ProgressEvent, ReadableStreamDefaultReader, RequestCredentials, RequestInit,
+    ResponseInit, XmlHttpRequest, XmlHttpRequestResponseType,
+};
+
+// ------ Progress ------
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Progress {
+    pub loaded_bytes: f64,
+    /// `None` when the size is unknown, e.g. the response has no `Content-Length`.
+    pub total_bytes: Option<f64>,
+}
+
+impl Progress {
+    /// Returns a number in the range `0.0..=100.0` or `None` when the size is unknown.
+    pub fn percent(&self) -> Option<f64> {
+        match self.total_bytes {
+            Some(total_bytes) if total_bytes > 0. => {
+                Some((self.loaded_bytes / total_bytes * 100.).min(100.))
+            }
+            Some(_) => Some(100.),
+            None => None,
+        }
+    }
+}
+
+// ------ AbortHandle ------
+
+/// Aborts the request and reading of its response body.
+/// The request is aborted also when the future returned from `Request::send` is dropped.
+#[derive(Clone)]
+pub struct AbortHandle(SendWrapper<AbortController>);
+
+impl AbortHandle {
+    fn new() -> Self {
+        Self(SendWrapper::new(AbortController::new().unwrap_throw()))
+    }
+
+    pub fn abort(&self) {
+        self.0.abort();
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.0.signal().aborted()
+    }
+}
+
+// ------ Body ------
+
+enum Body {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Body {
+    fn len(&self) -> usize {
+        match self {
+            Self::Text(text) => text.len(),
+            Self::Bytes(bytes) => bytes.len(),
+        }
+    }
+
+    fn to_js_value(&self) -> JsValue {
+        match self {
+            Self::Text(text) => JsValue::from(text.as_str()),
+            Self::Bytes(bytes) => js_sys::Uint8Array::from(bytes.as_slice()).into(),
+        }
+    }
+}
+
+// ------ Request ------
+
+pub struct Request {
+    method: &'static str,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Result<Option<Body>, FetchError>,
+    timeout: Option<u32>,
+    with_credentials: bool,
+    abort_handle: AbortHandle,
+    upload_progress: Option<Mutable<Progress>>,
+    download_progress: Option<Mutable<Progress>>,
+}
+
+impl Request {
+    pub fn new(method: &'static str, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            body: Ok(None),
+            timeout: None,
+            with_credentials: false,
+            abort_handle: AbortHandle::new(),
+            upload_progress: None,
+            download_progress: None,
+        }
+    }
+
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new("GET", url)
+    }
+
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new("POST", url)
+    }
+
+    pub fn put(url: impl Into<String>) -> Self {
+        Self::new("PUT", url)
+    }
+
+    pub fn patch(url: impl Into<String>) -> Self {
+        Self::new("PATCH", url)
+    }
+
+    pub fn delete(url: impl Into<String>) -> Self {
+        Self::new("DELETE", url)
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn text(mut self, body: impl Into<String>) -> Self {
+        self.body = Ok(Some(Body::Text(body.into())));
+        self
+    }
+
+    pub fn bytes(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Ok(Some(Body::Bytes(body.into())));
+        self
+    }
+
+    /// Sets also the `Content-Type` header.
+    /// A serialization error is returned from `send`.
+    #[cfg(feature = "serde")]
+    pub fn json<T: Serialize + ?Sized>(mut self, body: &T) -> Self {
+        self.body = serde_json::to_string(body)
+            .map(|body| Some(Body::Text(body)))
+            .map_err(FetchError::Json);
+        self.header("Content-Type", "application/json")
+    }
+    /// Sets also the `Content-Type` header.
+    /// A serialization error is returned from `send`.
+    #[cfg(feature = "serde-lite")]
+    pub fn json<T: Serialize + ?Sized>(mut self, body: &T) -> Self {
+        self.body = body
+            .serialize()
+            .map_err(FetchError::Serialization)
+            .and_then(|body| serde_json::to_string(&body).map_err(FetchError::Json))
+            .map(|body| Some(Body::Text(body)));
+        self.header("Content-Type", "application/json")
+    }
+
+    /// The request fails with `FetchError::Timeout` when it hasn't finished in time,
+    /// including reading of the response body (in milliseconds).
+    pub fn timeout(mut self, ms: u32) -> Self {
+        self.timeout = Some(ms);
+        self
+    }
+
+    /// Sends cookies also to other origins (`credentials: "include"`).
+    pub fn with_credentials(mut self, with_credentials: bool) -> Self {
+        self.with_credentials = with_credentials;
+        self
+    }
+
+    /// The request is sent by `XMLHttpRequest` instead of `fetch`
+    /// because `fetch` doesn't report sent bytes.
+    pub fn upload_progress(mut self, progress: Mutable<Progress>) -> Self {
+        self.upload_progress = Some(progress);
+        self
+    }
+
+    /// Updated while the response body is being read.
+    /// With `upload_progress`, it's updated by the `XMLHttpRequest` progress events
+    /// and the body is already loaded when `send` returns.
+    pub fn download_progress(mut self, progress: Mutable<Progress>) -> Self {
+        self.download_progress = Some(progress);
+        self
+    }
+
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.abort_handle.clone()
+    }
+
+    pub async fn send(self) -> Result<Response, FetchError> {
+        if self.upload_progress.is_some() {
+            return self.send_with_xhr().await;
+        }
+        let body = self.body?;
+        let abort_guard = AbortGuard::new(self.abort_handle, self.timeout);
+
+        // ---- RequestInit ----
+        let mut request_init = RequestInit::new();
+        request_init
+            .method(self.method)
+            .signal(Some(&abort_guard.abort_handle.0.signal()));
+        if let Some(body) = &body {
+            request_init.body(Some(&body.to_js_value()));
+        }
+        if self.with_credentials {
+            request_init.credentials(RequestCredentials::Include);
+        }
+
+        // ---- Request ----
+        let request = web_sys::Request::new_with_str_and_init(&self.url, &request_init)
+            .map_err(FetchError::RequestFailed)?;
+        let headers = request.headers();
+        for (name, value) in &self.headers {
+            headers
+                .set(name, value)
+                .map_err(FetchError::RequestFailed)?;
+        }
+
+        // ---- Response ----
+        let response = JsFuture::from(window().fetch_with_request(&request))
+            .await
+            .map_err(|error| abort_guard.error(error, FetchError::RequestFailed))?
+            .unchecked_into::<web_sys::Response>();
+
+        Ok(Response {
+            raw: SendWrapper::new(response),
+            download_progress: self.download_progress,
+            abort_guard,
+        })
+    }
+
+    async fn send_with_xhr(self) -> Result<Response, FetchError> {
+        let body = self.body?;
+        let upload_progress = self.upload_progress.unwrap_throw();
+        let abort_guard = AbortGuard::new(self.abort_handle, self.timeout);
+
+        // ---- XmlHttpRequest ----
+        let xhr = XmlHttpRequest::new().map_err(FetchError::RequestFailed)?;
+        xhr.open_with_async(self.method, &self.url, true)
+            .map_err(FetchError::RequestFailed)?;
+        xhr.set_with_credentials(self.with_credentials);
+        xhr.set_response_type(XmlHttpRequestResponseType::Arraybuffer);
+        for (name, value) in &self.headers {
+            xhr.set_request_header(name, value)
+                .map_err(FetchError::RequestFailed)?;
+        }
+
+        // ---- Listeners ----
+        // Closures are owned by JS, they are released together with the request.
+        let body_size = body.as_ref().map(|body| body.len() as f64);
+        upload_progress.set(Progress {
+            loaded_bytes: 0.,
+            total_bytes: body_size,
+        });
+        xhr.upload()
+            .map_err(FetchError::RequestFailed)?
+            .add_event_listener_with_callback(
+                "progress",
+                progress_handler(upload_progress.clone()).unchecked_ref(),
+            )
+            .map_err(FetchError::RequestFailed)?;
+
+        if let Some(download_progress) = &self.download_progress {
+            download_progress.set(Progress::default());
+            xhr.add_event_listener_with_callback(
+                "progress",
+                progress_handler(download_progress.clone()).unchecked_ref(),
+            )
+            .map_err(FetchError::RequestFailed)?;
+        }
+
+        let (loadend_sender, loadend_receiver) = oneshot::channel();
+        let loadend_handler = Closure::once_into_js(move || {
+            let _ = loadend_sender.send(());
+        });
+        xhr.add_event_listener_with_callback("loadend", loadend_handler.unchecked_ref())
+            .map_err(FetchError::RequestFailed)?;
+
+        // Aborted by `AbortHandle`, the timeout or by dropping the `send` future.
+        let abort_handler = Closure::once_into_js({
+            let xhr = xhr.clone();
+            move || {
+                let _ = xhr.abort();
+            }
+        });
+        abort_guard
+            .abort_handle
+            .0
+            .signal()
+            .add_event_listener_with_callback("abort", abort_handler.unchecked_ref())
+            .map_err(FetchError::RequestFailed)?;
+
+        // ---- Send ----
+        match &body {
+            Some(Body::Text(text)) => xhr.send_with_opt_str(Some(text)),
+            Some(body @ Body::Bytes(_)) => {
+                xhr.send_with_opt_buffer_source(Some(body.to_js_value().unchecked_ref()))
+            }
+            None => xhr.send(),
+        }
+        .map_err(FetchError::RequestFailed)?;
+        let _ = loadend_receiver.await;
+
+        // ---- Response ----
+        let status = xhr.status().map_err(FetchError::RequestFailed)?;
+        if status == 0 {
+            let error = JsValue::from("network error");
+            Err(abort_guard.error(error, FetchError::RequestFailed))?
+        }
+        let body_size = body_size.unwrap_or_default();
+        upload_progress.set(Progress {
+            loaded_bytes: body_size,
+            total_bytes: Some(body_size),
+        });
+
+        let bytes = match xhr.response().map_err(FetchError::ReadBodyFailed)? {
+            response if response.is_null() => js_sys::Uint8Array::new_with_length(0),
+            response => js_sys::Uint8Array::new(&response),
+        };
+        // The loaded response is wrapped in `web_sys::Response` to keep `Response` methods working.
+        let headers = web_sys::Headers::new().map_err(FetchError::RequestFailed)?;
+        let raw_headers = xhr
+            .get_all_response_headers()
+            .map_err(FetchError::RequestFailed)?;
+        for (name, value) in raw_headers
+            .split("\r\n")
+            .filter_map(|line| line.split_once(": "))
+        {
+            let _ = headers.append(name, value);
+        }
+        let mut response_init = ResponseInit::new();
+        response_init.status(status).headers(&headers);
+        // A non-empty body isn't allowed for statuses like `204`.
+        let body = (bytes.length() > 0).then(|| bytes.as_ref());
+        let response = web_sys::Response::new_with_opt_buffer_source_and_init(body, &response_init)
+            .map_err(FetchError::RequestFailed)?;
+
+        Ok(Response {
+            raw: SendWrapper::new(response),
+            download_progress: None,
+            abort_guard,
+        })
+    }
+}
+
+fn progress_handler(progress: Mutable<Progress>) -> JsValue {
+    Closure::<dyn FnMut(ProgressEvent)>::new(move |event: ProgressEvent| {
+        progress.set(Progress {
+            loaded_bytes: event.loaded(),
+            total_bytes: event.length_computable().then(|| event.total()),
+        });
+    })
+    .into_js_value()
+}
+
+// ------ AbortGuard ------
+
+// Aborts the request when dropped, e.g. when the `send` future or `Response` has been dropped.
+struct AbortGuard {
+    abort_handle: AbortHandle,
+    timed_out: Arc<AtomicBool>,
+    _timer: Option<Timer>,
+}
+
+impl AbortGuard {
+    fn new(abort_handle: AbortHandle, timeout: Option<u32>) -> Self {
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let timer = timeout.map(|timeout| {
+            let abort_handle = abort_handle.clone();
+            let timed_out = Arc::clone(&timed_out);
+            Timer::once(timeout, move || {
+                timed_out.store(true, Ordering::SeqCst);
+                abort_handle.abort();
+            })
+        });
+        Self {
+            abort_handle,
+            timed_out,
+            _timer: timer,
+        }
+    }
+
+    fn error(&self, error: JsValue, to_error: impl FnOnce(JsValue) -> FetchError) -> FetchError {
+        if self.timed_out.load(Ordering::SeqCst) {
+            return FetchError::Timeout;
+        }
+        if self.abort_handle.is_aborted() {
+            return FetchError::Aborted;
+        }
+        to_error(error)
+    }
+}
+
+impl Drop for AbortGuard {
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+    }
+}
+
+// ------ Response ------
+
+pub struct Response {
+    raw: SendWrapper<web_sys::Response>,
+    download_progress: Option<Mutable<Progress>>,
+    abort_guard: AbortGuard,
+}
+
+impl Response {
+    pub fn status(&self) -> u16 {
+        self.raw.status()
+    }
+
+    /// `true` for `2xx` statuses.
+    pub fn ok(&self) -> bool {
+        self.raw.ok()
+    }
+
+    pub fn header(&self, name: &str) -> Option<String> {
+        self.raw.headers().get(name).ok().flatten()
+    }
+
+    pub fn error_for_status(self) -> Result<Self, FetchError> {
+        if self.ok() {
+            return Ok(self);
+        }
+        Err(FetchError::StatusIsNot2xx(self.status()))
+    }
+
+    pub fn raw(&self) -> &web_sys::Response {
+        &self.raw
+    }
+
+    pub async fn text(self) -> Result<String, FetchError> {
+        String::from_utf8(self.bytes().await?).map_err(FetchError::InvalidUtf8)
+    }
+
+    #[cfg(feature = "serde")]
+    pub async fn json<T: DeserializeOwned>(self) -> Result<T, FetchError> {
+        serde_json::from_slice(&self.bytes().await?).map_err(FetchError::Json)
+    }
+    #[cfg(feature = "serde-lite")]
+    pub async fn json<T: Deserialize>(self) -> Result<T, FetchError> {
+        let value = serde_json::from_slice(&self.bytes().await?).map_err(FetchError::Json)?;
+        T::deserialize(&value).map_err(FetchError::Deserialization)
+    }
+
+    pub async fn bytes(self) -> Result<Vec<u8>, FetchError> {
+        match &self.download_progress {
+            Some(download_progress) => self.read_body_with_progress(download_progress).await,
+            None => {
+                let array_buffer = self
+                    .raw
+                    .array_buffer()
+                    .map_err(FetchError::ReadBodyFailed)?;
+                let array_buffer = JsFuture::from(array_buffer)
+                    .await
+                    .map_err(|error| self.abort_guard.error(error, FetchError::ReadBodyFailed))?;
+                Ok(js_sys::Uint8Array::new(&array_buffer).to_vec())
+            }
+        }
+    }
+
+    async fn read_body_with_progress(
+        &self,
+        download_progress: &Mutable<Progress>,
+    ) -> Result<Vec<u8>, FetchError> {
+        let total_bytes = self
+            .header("Content-Length")
+            .and_then(|length| length.parse().ok());
+        let mut progress = Progress {
+            loaded_bytes: 0.,
+            total_bytes,
+        };
+        download_progress.set(progress);
+
+        let body_stream = match self.raw.body() {
+            Some(body_stream) => body_stream,
+            None => return Ok(Vec::new()),
+        };
+        let reader = body_stream
+            .get_reader()
+            .unchecked_into::<ReadableStreamDefaultReader>();
+
+        let mut bytes = Vec::new();
+        loop {
+            let chunk = JsFuture::from(reader.read())
+                .await
+                .map_err(|error| self.abort_guard.error(error, FetchError::ReadBodyFailed))?;
+            let done = Reflect::get(&chunk, &JsValue::from("done"))
+                .map_err(FetchError::ReadBodyFailed)?
+                .is_truthy();
+            if done {
+                break;
+            }
+            let value = Reflect::get(&chunk, &JsValue::from("value"))
+                .map_err(FetchError::ReadBodyFailed)?
+                .unchecked_into::<js_sys::Uint8Array>();
+            bytes.extend(value.to_vec());
+
+            progress.loaded_bytes = bytes.len() as f64;
+            download_progress.set(progress);
+        }
+        Ok(bytes)
+    }
+}
+
+// ------ FetchError ------
+
+#[derive(Debug)]
+pub enum FetchError {
+    /// E.g. a network error or the request has been blocked by CORS.
+    RequestFailed(JsValue),
+    Aborted,
+    Timeout,
+    StatusIsNot2xx(u16),
+    ReadBodyFailed(JsValue),
+    InvalidUtf8(FromUtf8Error),
+    Json(serde_json::Error),
+    #[cfg(feature = "serde-lite")]
+    Serialization(serde_lite::Error),
+    #[cfg(feature = "serde-lite")]
+    Deserialization(serde_lite::Error),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RequestFailed(error) => write!(f, "request failed: {:?}", error),
+            Self::Aborted => write!(f, "request aborted"),
+            Self::Timeout => write!(f, "request timed out"),
+            Self::StatusIsNot2xx(status) => write!(f, "response status {} is not 2xx", status),
+            Self::ReadBodyFailed(error) => write!(f, "reading response body failed: {:?}", error),
+            Self::InvalidUtf8(error) => write!(f, "response body is not valid UTF-8: {}", error),
+            Self::Json(error) => write!(f, "JSON (de)serialization failed: {}", error),
+            #[cfg(feature = "serde-lite")]
+            Self::Serialization(error) => write!(f, "serialization failed: {}", error),
+            #[cfg(feature = "serde-lite")]
+            Self::Deserialization(error) => write!(f, "deserialization failed: {}", error),
+        }
+    }
+}
+
+impl Error for FetchError {}
@@ -1,6 +1,9 @@
 #[cfg(feature = "connection")]
 mod connection;
 
+#[cfg(feature = "fetch")]
+pub mod fetch;
+
 #[cfg(feature = "routing")]
 pub mod routing;
 
@@ -81,7 +84,9 @@ pub use web_sys;
 
 #[cfg(feature = "connection")]
 pub use connection::{
-    Connection, ExchangeMsgsError, MsgOptions, ReceiveDownMsgError, SendUpMsgError,
+    Connection, ConnectionStatus, ExchangeMsgsError, MockConnection, MockResponse, MsgConnection,
+    MsgOptions, ReceiveDownMsgError, RetryPolicy, SendUpMsgError, Upload, UploadError,
+    UploadProgress,
 };
 
 #[cfg(feature = "routing")]
@@ -90,7 +95,10 @@ pub use route_macro::route;
 pub use routing::{FromRouteSegments, RouteSegment, Router};
 
 #[cfg(feature = "moonlight")]
-pub use moonlight::{self, AuthToken, CorId, EntityId, Wrapper};
+pub use moonlight::{
+    self, entity_ids, AuthToken, CorId, EntityId, ServerError, TypedEntityId, Validate,
+    ValidationErrors, Wrapper,
+};
 
 #[cfg(feature = "panic_hook")]
 pub use console_error_panic_hook;
@@ -140,8 +148,18 @@ pub use serde_wasm_bindgen;
 
 pub static PUBLIC_URL: &str = "/_api/public/";
 
+// Moon adds the meta tag with a fingerprinted URL when `fingerprinted_public` is enabled.
+static CURRENT_PUBLIC_URL: once_cell::sync::Lazy<String> = once_cell::sync::Lazy::new(|| {
+    document()
+        .query_selector(r#"meta[name="moonzoon-public-url"]"#)
+        .ok()
+        .flatten()
+        .and_then(|meta| meta.get_attribute("content"))
+        .unwrap_or_else(|| PUBLIC_URL.to_owned())
+});
+
 pub fn public_url(path: impl AsRef<str>) -> String {
-    format!("{PUBLIC_URL}{}", path.as_ref())
+    format!("{}{}", CURRENT_PUBLIC_URL.as_str(), path.as_ref())
 }
 
 #[macro_export]
@@ -44,3 +44,14 @@ macro_rules! make_route_segment_impls {
     )
 }
 make_route_segment_impls!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+#[cfg(feature = "moonlight")]
+impl<K: moonlight::EntityIdKind> RouteSegment for moonlight::TypedEntityId<K> {
+    fn from_string_segment(segment: &str) -> Option<Self> {
+        segment.parse().ok()
+    }
+
+    fn into_string_segment(self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
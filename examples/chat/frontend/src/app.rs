@@ -22,12 +22,21 @@ fn new_message_text() -> &'static Mutable<String> {
     Mutable::new(String::new())
 }
 
+#[cfg(not(test))]
 #[static_ref]
 pub fn connection() -> &'static Connection<UpMsg, DownMsg> {
-    Connection::new(|DownMsg::MessageReceived(message), _| {
-        messages().lock_mut().push_cloned(message);
-        jump_to_bottom();
-    })
+    Connection::new(down_msg_handler)
+}
+
+#[cfg(test)]
+#[static_ref]
+pub fn connection() -> &'static MockConnection<UpMsg, DownMsg> {
+    MockConnection::new(down_msg_handler)
+}
+
+fn down_msg_handler(DownMsg::MessageReceived(message): DownMsg, _: CorId) {
+    messages().lock_mut().push_cloned(message);
+    jump_to_bottom();
 }
 
 #[static_ref]
@@ -48,19 +57,69 @@ fn set_new_message_text(text: String) {
 }
 
 fn send_message() {
-    Task::start(async {
-        let result = connection()
-            .send_up_msg(UpMsg::SendMessage(Message {
-                username: username().get_cloned(),
-                text: new_message_text().take(),
-            }))
-            .await;
-        if let Err(error) = result {
-            eprintln!("Failed to send message: {:?}", error);
-        }
-    });
+    Task::start(send_new_message());
+}
+
+async fn send_new_message() {
+    let result = connection()
+        .send_up_msg(UpMsg::SendMessage(Message {
+            username: username().get_cloned(),
+            text: new_message_text().take(),
+        }))
+        .await;
+    if let Err(error) = result {
+        eprintln!("Failed to send message: {:?}", error);
+    }
 }
 
 fn jump_to_bottom() {
     received_messages_viewport_y().set(i32::MAX);
 }
+
+// ------ ------
+//     Tests
+// ------ ------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    async fn send_message_sends_up_msg() {
+        // ------ ARRANGE ------
+        set_username("Jane".to_owned());
+        set_new_message_text("Hello".to_owned());
+        connection().expect_up_msg(
+            |UpMsg::SendMessage(message)| message.username == "Jane" && message.text == "Hello",
+            MockResponse::Accepted,
+        );
+
+        // ------ ACT ------
+        send_new_message().await;
+
+        // ------ ASSERT ------
+        connection().assert_expectations_met();
+        assert!(new_message_text().lock_ref().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn down_msg_adds_message() {
+        // ------ ARRANGE ------
+        let message = Message {
+            username: "Joe".to_owned(),
+            text: "Hi".to_owned(),
+        };
+
+        // ------ ACT ------
+        connection().push_down_msg(DownMsg::MessageReceived(message), CorId::new());
+
+        // ------ ASSERT ------
+        let last_text = messages()
+            .lock_ref()
+            .last()
+            .map(|message| message.text.clone());
+        assert_eq!(last_text.as_deref(), Some("Hi"));
+        assert_eq!(received_messages_viewport_y().get(), i32::MAX);
+    }
+}
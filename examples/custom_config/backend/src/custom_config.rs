@@ -1,10 +1,7 @@
 use moon::*;
 
-// @TODO improve API & refactor the code below and:
-// @TODO return `Result` from `from_env_vars`? +(rename?)
-// @TODO write a derive macro `FromEnvVars`?
-
-pub static CUSTOM_CONFIG: Lazy<CustomConfig> = Lazy::new(CustomConfig::from_env_vars);
+// Loaded from `MoonZoonCustom.toml` or from env variables (e.g. `POSTGRES_USER_NAME`).
+pub static CUSTOM_CONFIG: Lazy<CustomConfig> = Lazy::new(config::custom_or_exit);
 
 #[derive(Debug, Deserialize)]
 #[serde(crate = "serde")]
@@ -14,36 +11,20 @@ pub struct CustomConfig {
     pub favorite_languages: Vec<String>,
     pub is_pig_pink: bool,
     pub birthday: DateTime<Local>,
-    #[serde(default = "Postgres::from_env_vars")]
     pub postgres: Postgres,
 }
 
-impl FromEnvVars for CustomConfig {
-    const ENTITY_NAME: &'static str = "CustomConfig";
-}
-
 #[derive(Debug, Deserialize)]
 #[serde(crate = "serde")]
 pub struct Postgres {
-    #[serde(default = "PostgresUser::from_env_vars")]
     pub user: PostgresUser,
     pub host: String,
     pub name: String,
 }
 
-impl FromEnvVars for Postgres {
-    const ENTITY_NAME: &'static str = "Postgres";
-    const ENV_PREFIX: &'static str = "POSTGRES_";
-}
-
 #[derive(Debug, Deserialize)]
 #[serde(crate = "serde")]
 pub struct PostgresUser {
     pub name: String,
     pub password: String,
 }
-
-impl FromEnvVars for PostgresUser {
-    const ENTITY_NAME: &'static str = "PostgresUser";
-    const ENV_PREFIX: &'static str = "POSTGRES_USER_";
-}
@@ -1,4 +1,4 @@
-use zoon::{eprintln, routing::origin, *};
+use zoon::{eprintln, fetch, *};
 
 // ------ ------
 //     Types
@@ -25,11 +25,10 @@ fn stars() -> &'static Mutable<Stars> {
 // ------ ------
 
 fn load_stars() {
-    async fn stars_request() -> reqwest::Result<u32> {
-        // @TODO remove `origin()` once a wasm-compatible HTTP client supports relative urls
-        // Surf issue: https://github.com/http-rs/surf/issues/314
-        // Reqwest issue: https://github.com/seanmonstar/reqwest/issues/988
-        Ok(reqwest::get(origin() + "/_api/moonzoon_stars")
+    async fn stars_request() -> Result<u32, fetch::FetchError> {
+        Ok(fetch::Request::get("/_api/moonzoon_stars")
+            .timeout(10_000)
+            .send()
             .await?
             .error_for_status()?
             .text()
@@ -430,5 +430,5 @@ async fn up_msg_handler(req: UpMsgRequest<UpMsg>) {
 #[moon::main]
 async fn main() -> std::io::Result<()> {
     // init().await;
-    start(frontend, up_msg_handler, |_| {}).await
+    start(frontend, validated(up_msg_handler), |_| {}).await
 }
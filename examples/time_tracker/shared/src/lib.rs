@@ -4,12 +4,14 @@ pub mod clients_and_projects;
 pub mod time_blocks;
 pub mod time_tracker;
 
-pub type ClientId = EntityId;
-pub type ProjectId = EntityId;
-pub type TimeBlockId = EntityId;
-pub type InvoiceId = EntityId;
-pub type TimeEntryId = EntityId;
-pub type UserId = EntityId;
+entity_ids! {
+    pub ClientId,
+    pub ProjectId,
+    pub TimeBlockId,
+    pub InvoiceId,
+    pub TimeEntryId,
+    pub UserId,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(crate = "serde")]
@@ -21,11 +23,16 @@ pub struct User {
 
 // ------ UpMsg ------
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 #[serde(crate = "serde")]
 pub enum UpMsg {
     // ------ Auth ------
-    Login { name: String, password: String },
+    Login {
+        #[validate(length(min = 1, max = 100))]
+        name: String,
+        #[validate(length(min = 1))]
+        password: String,
+    },
     Logout,
     // ------ Page data ------
     GetClientsAndProjectsClients,